@@ -0,0 +1,28 @@
+//! Plays the game with a bot, without a window or GPU, faster than real time.
+//!
+//! `cargo run --release --example headless -- --runs 20 --seed 1`
+
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy_game::HeadlessGamePlugin;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+
+    App::new()
+        .add_plugins((
+            MinimalPlugins,
+            LogPlugin::default(),
+            HeadlessGamePlugin {
+                runs: value("--runs").unwrap_or(1) as u32,
+                seed: value("--seed"),
+            },
+        ))
+        .run();
+}
@@ -0,0 +1,86 @@
+//! Late-night collision load: thousands of enemies and a screen full of bullets,
+//! checked pair by pair and through the spatial grid the game uses.
+//!
+//! `cargo run --release --example spatial_grid -- --enemies 5000 --bullets 1000`
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy_game::collision::Collider;
+use bevy_game::spatial_grid::SpatialGrid;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Simulation steps measured, one second of play
+const STEPS: u32 = 60;
+/// Half the side of the square everything is scattered over
+const AREA: f32 = 1500.0;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+            .and_then(|value| value.parse::<usize>().ok())
+    };
+    let enemy_count = value("--enemies").unwrap_or(5000);
+    let bullet_count = value("--bullets").unwrap_or(1000);
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut scatter = |scale: f32| {
+        let position = Vec3::new(rng.gen_range(-AREA..AREA), rng.gen_range(-AREA..AREA), 0.0);
+        Transform::from_translation(position).with_scale(Vec3::splat(scale))
+    };
+    let enemies: Vec<(Entity, Transform)> = (0..enemy_count)
+        .map(|i| (Entity::from_raw(i as u32), scatter(0.25)))
+        .collect();
+    let bullets: Vec<Transform> = (0..bullet_count).map(|_| scatter(0.25)).collect();
+    let enemy_collider = Collider::circle(80.0);
+    let bullet_collider = Collider::circle(16.0);
+
+    let (pairwise_hits, pairwise) = measure(|| {
+        let mut hits = 0;
+        for bullet in &bullets {
+            for (_, enemy) in &enemies {
+                if bullet_collider.overlaps(bullet, &enemy_collider, enemy) {
+                    hits += 1;
+                }
+            }
+        }
+        hits
+    });
+
+    let mut grid = SpatialGrid::default();
+    let (grid_hits, with_grid) = measure(|| {
+        grid.clear();
+        for (entity, transform) in &enemies {
+            grid.insert(*entity, transform.translation.truncate(), enemy_collider.extent(transform));
+        }
+        let mut hits = 0;
+        for bullet in &bullets {
+            for entity in grid.near(bullet.translation.truncate(), bullet_collider.extent(bullet)) {
+                let (_, enemy) = &enemies[entity.index() as usize];
+                if bullet_collider.overlaps(bullet, &enemy_collider, enemy) {
+                    hits += 1;
+                }
+            }
+        }
+        hits
+    });
+
+    assert_eq!(pairwise_hits, grid_hits, "the grid missed or invented hits");
+    println!("{} enemies, {} bullets, {} hits per step", enemy_count, bullet_count, grid_hits);
+    println!("pair by pair: {:?} per step", pairwise / STEPS);
+    println!("spatial grid: {:?} per step, including the rebuild", with_grid / STEPS);
+}
+
+/// Runs `step` [`STEPS`] times, returns what the last run returned and the total time
+fn measure(mut step: impl FnMut() -> usize) -> (usize, Duration) {
+    let start = Instant::now();
+    let mut result = 0;
+    for _ in 0..STEPS {
+        result = std::hint::black_box(step());
+    }
+    (result, start.elapsed())
+}
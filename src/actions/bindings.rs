@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+use super::game_control::GameControl;
+
+pub const BINDINGS_FILE: &str = "controls.ron";
+
+/// A single physical input that can trigger a [`GameControl`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Pad(GamepadButtonType),
+}
+
+impl InputBinding {
+    pub fn label(&self) -> String {
+        match self {
+            InputBinding::Key(key) => format!("{:?}", key),
+            InputBinding::Mouse(button) => format!("Mouse {:?}", button),
+            InputBinding::Pad(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+/// Which inputs trigger which control. Loaded from [`BINDINGS_FILE`] at startup,
+/// falls back to the defaults if the file is missing or broken.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct Bindings {
+    pub controls: BTreeMap<GameControl, Vec<InputBinding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use InputBinding::*;
+        let mut controls = BTreeMap::new();
+        controls.insert(
+            GameControl::Up,
+            vec![
+                Key(KeyCode::W),
+                Key(KeyCode::Up),
+                Pad(GamepadButtonType::DPadUp),
+            ],
+        );
+        controls.insert(
+            GameControl::Down,
+            vec![
+                Key(KeyCode::S),
+                Key(KeyCode::Down),
+                Pad(GamepadButtonType::DPadDown),
+            ],
+        );
+        controls.insert(
+            GameControl::Left,
+            vec![
+                Key(KeyCode::A),
+                Key(KeyCode::Left),
+                Pad(GamepadButtonType::DPadLeft),
+            ],
+        );
+        controls.insert(
+            GameControl::Right,
+            vec![
+                Key(KeyCode::D),
+                Key(KeyCode::Right),
+                Pad(GamepadButtonType::DPadRight),
+            ],
+        );
+        controls.insert(
+            GameControl::Fire,
+            vec![
+                Mouse(MouseButton::Left),
+                Pad(GamepadButtonType::RightTrigger2),
+            ],
+        );
+        controls.insert(
+            GameControl::PlaceTurret,
+            vec![Key(KeyCode::E), Pad(GamepadButtonType::West)],
+        );
+        controls.insert(
+            GameControl::SwapWeapon,
+            vec![Key(KeyCode::R), Pad(GamepadButtonType::North)],
+        );
+        controls.insert(
+            GameControl::OpenChest,
+            vec![Key(KeyCode::F), Pad(GamepadButtonType::South)],
+        );
+        controls.insert(
+            GameControl::SaveAndQuit,
+            vec![Key(KeyCode::Escape), Pad(GamepadButtonType::Start)],
+        );
+        Self { controls }
+    }
+}
+
+impl Bindings {
+    /// ZQSD movement for AZERTY keyboards, everything else as default
+    pub fn azerty() -> Self {
+        use InputBinding::*;
+        let mut bindings = Self::default();
+        bindings.set(
+            GameControl::Up,
+            vec![
+                Key(KeyCode::Z),
+                Key(KeyCode::Up),
+                Pad(GamepadButtonType::DPadUp),
+            ],
+        );
+        bindings.set(
+            GameControl::Left,
+            vec![
+                Key(KeyCode::Q),
+                Key(KeyCode::Left),
+                Pad(GamepadButtonType::DPadLeft),
+            ],
+        );
+        bindings
+    }
+
+    /// Arrows and the keys around them, so the mouse can stay in the left hand
+    pub fn left_handed() -> Self {
+        use InputBinding::*;
+        let mut bindings = Self::default();
+        bindings.set(
+            GameControl::Up,
+            vec![
+                Key(KeyCode::Up),
+                Key(KeyCode::O),
+                Pad(GamepadButtonType::DPadUp),
+            ],
+        );
+        bindings.set(
+            GameControl::Down,
+            vec![
+                Key(KeyCode::Down),
+                Key(KeyCode::L),
+                Pad(GamepadButtonType::DPadDown),
+            ],
+        );
+        bindings.set(
+            GameControl::Left,
+            vec![
+                Key(KeyCode::Left),
+                Key(KeyCode::K),
+                Pad(GamepadButtonType::DPadLeft),
+            ],
+        );
+        bindings.set(
+            GameControl::Right,
+            vec![
+                Key(KeyCode::Right),
+                Key(KeyCode::Semicolon),
+                Pad(GamepadButtonType::DPadRight),
+            ],
+        );
+        bindings.set(
+            GameControl::PlaceTurret,
+            vec![Key(KeyCode::P), Pad(GamepadButtonType::West)],
+        );
+        bindings.set(
+            GameControl::SwapWeapon,
+            vec![Key(KeyCode::I), Pad(GamepadButtonType::North)],
+        );
+        bindings.set(
+            GameControl::OpenChest,
+            vec![Key(KeyCode::U), Pad(GamepadButtonType::South)],
+        );
+        bindings
+    }
+
+    pub fn get(&self, control: GameControl) -> &[InputBinding] {
+        self.controls
+            .get(&control)
+            .map(|b| b.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Short text for prompts, like "press R"
+    pub fn describe(&self, control: GameControl) -> String {
+        match self.get(control).first() {
+            Some(binding) => binding.label(),
+            None => "(unbound)".into(),
+        }
+    }
+
+    pub fn set(&mut self, control: GameControl, inputs: Vec<InputBinding>) {
+        self.controls.insert(control, inputs);
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = storage::config_file(BINDINGS_FILE) else {
+            return Self::default();
+        };
+        let Some(text) = storage::read_to_string(&path) else {
+            info!("No controls file at {:?}, using default bindings", path);
+            return Self::default();
+        };
+        match ron::from_str::<Bindings>(&text) {
+            Ok(mut bindings) => {
+                // Controls added after the file was written keep their defaults
+                for (control, inputs) in Self::default().controls {
+                    bindings.controls.entry(control).or_insert(inputs);
+                }
+                bindings
+            }
+            Err(e) => {
+                warn!("Can't parse {:?}: {}, using default bindings", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = storage::config_file(BINDINGS_FILE) else {
+            return;
+        };
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("Can't serialize bindings: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = storage::write(&path, &text) {
+            warn!("Can't save bindings to {:?}: {}", path, e);
+        }
+    }
+}
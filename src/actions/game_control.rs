@@ -1,33 +1,78 @@
-use bevy::prelude::{Input, KeyCode, Res};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{GamepadButton, Gamepads, Input, KeyCode, MouseButton, Res};
+use serde::{Deserialize, Serialize};
 
+use super::bindings::{Bindings, InputBinding};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub enum GameControl {
     Up,
     Down,
     Left,
     Right,
+    Fire,
+    PlaceTurret,
+    SwapWeapon,
+    OpenChest,
+    SaveAndQuit,
 }
 
 impl GameControl {
-    pub fn pressed(&self, keyboard_input: &Res<Input<KeyCode>>) -> bool {
+    pub const ALL: [GameControl; 9] = [
+        GameControl::Up,
+        GameControl::Down,
+        GameControl::Left,
+        GameControl::Right,
+        GameControl::Fire,
+        GameControl::PlaceTurret,
+        GameControl::SwapWeapon,
+        GameControl::OpenChest,
+        GameControl::SaveAndQuit,
+    ];
+
+    pub fn label(&self) -> &'static str {
         match self {
-            GameControl::Up => {
-                keyboard_input.pressed(KeyCode::W) || keyboard_input.pressed(KeyCode::Up)
-            }
-            GameControl::Down => {
-                keyboard_input.pressed(KeyCode::S) || keyboard_input.pressed(KeyCode::Down)
-            }
-            GameControl::Left => {
-                keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left)
-            }
-            GameControl::Right => {
-                keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right)
-            }
+            GameControl::Up => "Move up",
+            GameControl::Down => "Move down",
+            GameControl::Left => "Move left",
+            GameControl::Right => "Move right",
+            GameControl::Fire => "Fire",
+            GameControl::PlaceTurret => "Place turret",
+            GameControl::SwapWeapon => "Take offered item",
+            GameControl::OpenChest => "Open chest",
+            GameControl::SaveAndQuit => "Save and quit",
         }
     }
 }
 
-pub fn get_movement(control: GameControl, input: &Res<Input<KeyCode>>) -> f32 {
-    if control.pressed(input) {
+/// Raw keyboard, mouse and gamepad state read through the current [`Bindings`]
+#[derive(SystemParam)]
+pub struct ControlInput<'w> {
+    pub bindings: Res<'w, Bindings>,
+    keyboard: Res<'w, Input<KeyCode>>,
+    mouse: Res<'w, Input<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    pad_buttons: Res<'w, Input<GamepadButton>>,
+}
+
+impl<'w> ControlInput<'w> {
+    pub fn pressed(&self, control: GameControl) -> bool {
+        self.bindings
+            .get(control)
+            .iter()
+            .any(|binding| match binding {
+                InputBinding::Key(key) => self.keyboard.pressed(*key),
+                InputBinding::Mouse(button) => self.mouse.pressed(*button),
+                InputBinding::Pad(button) => self.gamepads.iter().any(|gamepad| {
+                    self.pad_buttons
+                        .pressed(GamepadButton::new(gamepad, *button))
+                }),
+            })
+    }
+}
+
+pub fn get_movement(control: GameControl, input: &ControlInput) -> f32 {
+    if input.pressed(control) {
         1.0
     } else {
         0.0
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+
+/// Stick values below this length are ignored for movement
+pub const MOVE_DEADZONE: f32 = 0.2;
+/// Aiming needs a firmer push, so a resting thumb doesn't snap the aim around
+pub const AIM_DEADZONE: f32 = 0.35;
+
+/// Radial deadzone, the remaining range is rescaled to 0..1
+pub fn apply_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= deadzone {
+        Vec2::ZERO
+    } else {
+        stick / length * ((length - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
+
+/// Position of one of the sticks of the first connected gamepad
+pub fn read_stick(
+    gamepads: &Gamepads,
+    axes: &Axis<GamepadAxis>,
+    x: GamepadAxisType,
+    y: GamepadAxisType,
+) -> Vec2 {
+    let Some(gamepad) = gamepads.iter().next() else {
+        return Vec2::ZERO;
+    };
+    Vec2::new(
+        axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.0),
+        axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inside_the_deadzone_is_zero() {
+        assert_eq!(apply_deadzone(Vec2::new(0.1, 0.1), MOVE_DEADZONE), Vec2::ZERO);
+        assert_eq!(apply_deadzone(Vec2::new(0.0, MOVE_DEADZONE), MOVE_DEADZONE), Vec2::ZERO);
+    }
+
+    #[test]
+    fn outside_the_deadzone_is_rescaled() {
+        let halfway = apply_deadzone(Vec2::new(0.6, 0.0), MOVE_DEADZONE);
+        assert!((halfway - Vec2::new(0.5, 0.0)).length() < 1e-6);
+
+        // The direction is kept
+        let diagonal = apply_deadzone(Vec2::new(0.5, 0.5), MOVE_DEADZONE);
+        assert!((diagonal.x - diagonal.y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn full_push_is_at_most_one() {
+        // Some pads report corners past the unit circle
+        let corner = apply_deadzone(Vec2::new(1.0, 1.0), AIM_DEADZONE);
+        assert!((corner.length() - 1.0).abs() < 1e-6);
+    }
+}
@@ -1,80 +1,263 @@
-
-
+use bevy::input::InputSystem;
 use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
-use crate::actions::game_control::{get_movement, GameControl};
+use crate::actions::bindings::Bindings;
+use crate::actions::game_control::{get_movement, ControlInput, GameControl};
+use crate::actions::gamepad::{apply_deadzone, read_stick, AIM_DEADZONE, MOVE_DEADZONE};
+use crate::actions::touch::{
+    draw_virtual_sticks, spawn_touch_buttons, update_touch_buttons, update_touch_controls,
+    TouchControls,
+};
 use crate::characters::player::Player;
 use crate::GameState;
 
-mod game_control;
-
-pub const FOLLOW_EPSILON: f32 = 5.;
+pub mod bindings;
+pub mod game_control;
+pub mod gamepad;
+pub mod touch;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions
+// This plugin listens for keyboard, mouse, gamepad and touch input and converts the input into Actions
 // Actions can then be used as a resource in other systems to act on the player input.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Actions>().add_systems(
-            Update,
-            (set_movement_actions, mouse_control).run_if(in_state(GameState::Playing)),
-        );
+        app.init_resource::<TouchControls>()
+            .insert_resource(Bindings::load())
+            .add_systems(
+                PreUpdate,
+                (
+                    update_touch_controls,
+                    (
+                        set_movement_actions,
+                        set_verb_actions,
+                        (mouse_control, gamepad_aim, touch_aim).chain(),
+                    ),
+                )
+                    .chain()
+                    .in_set(ActionsSet)
+                    .after(InputSystem)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::Playing), spawn_touch_buttons)
+            .add_systems(
+                Update,
+                (update_touch_buttons, draw_virtual_sticks).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Systems filling [`Actions`], gameplay systems run after them and only read [`Actions`]
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ActionsSet;
+
+/// State of a discrete verb, like fire or interact
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ActionState {
+    pub pressed: bool,
+    pub just_pressed: bool,
+}
+
+impl ActionState {
+    /// Feed the current pressed state, `just_pressed` is derived from the previous one.
+    /// It stays set until a simulation step consumes it, so a press is never lost on a frame without a step.
+    pub fn update(&mut self, pressed: bool) {
+        self.just_pressed |= pressed && !self.pressed;
+        self.pressed = pressed;
     }
 }
 
+/// The device that aimed last, the other one is ignored until it is used again
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AimDevice {
+    #[default]
+    Mouse,
+    Gamepad,
+    Touch,
+}
+
+pub const PAD_AIM_DISTANCE: f32 = 200.;
+
 #[derive(Default, Resource)]
 pub struct Actions {
     pub player_movement: Option<Vec2>,
-    pub mouse_angle: f32
+    /// Rotation around Z that turns the player's `Vec3::Y` towards the aim point
+    pub mouse_angle: f32,
+    /// Where the player aims, in world space
+    pub aim_point: Vec2,
+    pub aim_device: AimDevice,
+    pub fire: ActionState,
+    pub interact: ActionState,
+    pub place_turret: ActionState,
+    pub accept_offer: ActionState,
+    /// Handled outside the simulation, so it isn't consumed by a step nor recorded in replays
+    pub save_and_quit: ActionState,
 }
 
+impl Actions {
+    pub fn consume_presses(&mut self) {
+        for state in [
+            &mut self.fire,
+            &mut self.interact,
+            &mut self.place_turret,
+            &mut self.accept_offer,
+        ] {
+            state.just_pressed = false;
+        }
+    }
+}
 
+/// Nothing carries over from the previous run, replays rely on it
+pub fn reset_actions(mut actions: ResMut<Actions>) {
+    *actions = Actions::default();
+}
+
+/// Angle for [`Actions::mouse_angle`] from a world space direction
+pub fn aim_angle(direction: Vec2) -> f32 {
+    Vec2::Y.angle_between(direction)
+}
 
 pub fn mouse_control(
     primary_window: Query<&Window, With<PrimaryWindow>>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mut actions: ResMut<Actions>,
+    player: Query<&Transform, With<Player>>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+) {
+    if cursor_moved.iter().last().is_some() {
+        actions.aim_device = AimDevice::Mouse;
+    }
+    if actions.aim_device != AimDevice::Mouse {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform)), Ok(player)) = (
+        primary_window.get_single(),
+        camera.get_single(),
+        player.get_single(),
+    ) else {
+        return;
+    };
+    // The camera lags behind the player, so aim from the player and not from the screen centre
+    if let Some(aim_point) = window
+        .cursor_position()
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position))
+    {
+        let direction = aim_point - player.translation.xy();
+        if direction != Vec2::ZERO {
+            actions.mouse_angle = aim_angle(direction);
+        }
+        actions.aim_point = aim_point;
+    }
+}
+
+pub fn set_verb_actions(
+    mut actions: ResMut<Actions>,
+    control_input: ControlInput,
+    touch_controls: Res<TouchControls>,
+) {
+    let pressed = |control| control_input.pressed(control) || touch_controls.pressed(control);
+    actions
+        .fire
+        .update(pressed(GameControl::Fire) || touch_controls.firing());
+    actions.interact.update(pressed(GameControl::OpenChest));
+    actions
+        .place_turret
+        .update(pressed(GameControl::PlaceTurret));
+    actions
+        .accept_offer
+        .update(pressed(GameControl::SwapWeapon));
+    actions
+        .save_and_quit
+        .update(pressed(GameControl::SaveAndQuit));
+}
+
+pub fn gamepad_aim(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
     mut actions: ResMut<Actions>,
+    player: Query<&Transform, With<Player>>,
 ) {
-    if let Ok(window) = primary_window.get_single() {
-        if let Some(position) = window.cursor_position() {
-            let win_size = Vec2 {x: window.width(), y: window.height() } / 2.0;
-            let angle = (position - win_size).angle_between(Vec2::NEG_Y);
-            actions.mouse_angle = angle;
+    let stick = read_stick(
+        &gamepads,
+        &axes,
+        GamepadAxisType::RightStickX,
+        GamepadAxisType::RightStickY,
+    );
+    let stick = apply_deadzone(stick, AIM_DEADZONE);
+    if stick != Vec2::ZERO {
+        actions.aim_device = AimDevice::Gamepad;
+        actions.mouse_angle = aim_angle(stick);
+    }
+    if actions.aim_device == AimDevice::Gamepad {
+        if let Ok(player) = player.get_single() {
+            stick_aim_point(&mut actions, player);
         }
     }
+}
 
+pub fn touch_aim(
+    touch_controls: Res<TouchControls>,
+    mut actions: ResMut<Actions>,
+    player: Query<&Transform, With<Player>>,
+) {
+    if let Some(stick) = &touch_controls.aim_stick {
+        let stick = apply_deadzone(stick.value(), AIM_DEADZONE);
+        if stick != Vec2::ZERO {
+            actions.aim_device = AimDevice::Touch;
+            actions.mouse_angle = aim_angle(stick);
+        }
+    }
+    if actions.aim_device == AimDevice::Touch {
+        if let Ok(player) = player.get_single() {
+            stick_aim_point(&mut actions, player);
+        }
+    }
 }
 
+/// Sticks only give a direction, keep the aim point at a fixed distance from the player
+fn stick_aim_point(actions: &mut Actions, player: &Transform) {
+    let direction = Quat::from_rotation_z(actions.mouse_angle) * Vec3::Y;
+    actions.aim_point = player.translation.xy() + direction.xy() * PAD_AIM_DISTANCE;
+}
 
 pub fn set_movement_actions(
     mut actions: ResMut<Actions>,
-    keyboard_input: Res<Input<KeyCode>>,
-    touch_input: Res<Touches>,
-    player: Query<&Transform, With<Player>>,
-    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    control_input: ControlInput,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    touch_controls: Res<TouchControls>,
 ) {
     let mut player_movement = Vec2::new(
-        get_movement(GameControl::Right, &keyboard_input)
-            - get_movement(GameControl::Left, &keyboard_input),
-        get_movement(GameControl::Up, &keyboard_input)
-            - get_movement(GameControl::Down, &keyboard_input),
+        get_movement(GameControl::Right, &control_input)
+            - get_movement(GameControl::Left, &control_input),
+        get_movement(GameControl::Up, &control_input)
+            - get_movement(GameControl::Down, &control_input),
+    )
+    .normalize_or_zero();
+
+    // The sticks are analog, a half push walks at half speed
+    let stick = read_stick(
+        &gamepads,
+        &axes,
+        GamepadAxisType::LeftStickX,
+        GamepadAxisType::LeftStickY,
     );
+    let stick = apply_deadzone(stick, MOVE_DEADZONE);
+    if stick != Vec2::ZERO {
+        player_movement = stick;
+    }
 
-    if let Some(touch_position) = touch_input.first_pressed_position() {
-        let (camera, camera_transform) = camera.single();
-        if let Some(touch_position) = camera.viewport_to_world_2d(camera_transform, touch_position)
-        {
-            let diff = touch_position - player.single().translation.xy();
-            if diff.length() > FOLLOW_EPSILON {
-                player_movement = diff.normalize();
-            }
+    if let Some(stick) = &touch_controls.move_stick {
+        let stick = apply_deadzone(stick.value(), MOVE_DEADZONE);
+        if stick != Vec2::ZERO {
+            player_movement = stick;
         }
     }
 
     if player_movement != Vec2::ZERO {
-        actions.player_movement = Some(player_movement.normalize());
+        actions.player_movement = Some(player_movement);
     } else {
         actions.player_movement = None;
     }
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{
+    egui::{self, Color32, Pos2, Stroke},
+    EguiContexts,
+};
+
+use crate::characters::player::{Inventoty, Player, TemporaryItems};
+use crate::collision::Collider;
+use crate::interactive_items::chest::Chest;
+use crate::loading::FontAssets;
+use crate::simulation::SimulationTime;
+
+use super::game_control::GameControl;
+use super::gamepad::AIM_DEADZONE;
+
+/// How far, in logical pixels, a finger has to move from where it landed to push a stick fully
+pub const STICK_RADIUS: f32 = 60.;
+
+/// A stick that appears where a finger lands and follows it until it's lifted
+pub struct VirtualStick {
+    pub touch_id: u64,
+    pub origin: Vec2,
+    pub position: Vec2,
+}
+
+impl VirtualStick {
+    /// Stick value in world orientation (Y up), length up to 1
+    pub fn value(&self) -> Vec2 {
+        let offset = (self.position - self.origin) / STICK_RADIUS;
+        Vec2::new(offset.x, -offset.y).clamp_length_max(1.0)
+    }
+}
+
+/// On-screen controls: the left half of the screen moves, the right half aims and fires.
+/// Only active once the screen has been touched.
+#[derive(Resource, Default)]
+pub struct TouchControls {
+    pub enabled: bool,
+    pub move_stick: Option<VirtualStick>,
+    pub aim_stick: Option<VirtualStick>,
+    /// Touches that landed on a [`TouchButton`]
+    buttons: HashMap<u64, GameControl>,
+}
+
+impl TouchControls {
+    pub fn pressed(&self, control: GameControl) -> bool {
+        self.buttons.values().any(|c| *c == control)
+    }
+
+    /// Aiming with the right stick fires automatically
+    pub fn firing(&self) -> bool {
+        self.aim_stick
+            .as_ref()
+            .is_some_and(|stick| stick.value().length() > AIM_DEADZONE)
+    }
+}
+
+#[derive(Component)]
+pub struct TouchButton(pub GameControl);
+
+pub fn spawn_touch_buttons(mut commands: Commands, font_assets: Res<FontAssets>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.0),
+                top: Val::Percent(25.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (control, label) in [
+                (GameControl::OpenChest, "Open"),
+                (GameControl::SwapWeapon, "Swap"),
+                (GameControl::PlaceTurret, "Turret"),
+                (GameControl::SaveAndQuit, "Quit"),
+            ] {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            width: Val::Px(120.0),
+                            height: Val::Px(60.0),
+                            margin: UiRect::all(Val::Px(8.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::rgba(0.15, 0.15, 0.15, 0.6).into(),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    })
+                    .insert(TouchButton(control))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 32.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+pub fn update_touch_controls(
+    mut touch_controls: ResMut<TouchControls>,
+    touches: Res<Touches>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    buttons: Query<(&TouchButton, &Node, &GlobalTransform, &Visibility)>,
+) {
+    let touch_controls = touch_controls.as_mut();
+    if touches.any_just_pressed() {
+        touch_controls.enabled = true;
+    }
+    if !touch_controls.enabled {
+        return;
+    }
+    let half_width = primary_window
+        .get_single()
+        .map(|window| window.width() / 2.0)
+        .unwrap_or(0.0);
+
+    for touch in touches.iter_just_pressed() {
+        let position = touch.position();
+        let button = buttons.iter().find(|(_, node, transform, visibility)| {
+            let center = transform.translation().truncate();
+            let half_size = node.size() / 2.0;
+            **visibility != Visibility::Hidden && (position - center).abs().cmple(half_size).all()
+        });
+        if let Some((button, ..)) = button {
+            touch_controls.buttons.insert(touch.id(), button.0);
+        } else if position.x < half_width {
+            if touch_controls.move_stick.is_none() {
+                touch_controls.move_stick = Some(VirtualStick {
+                    touch_id: touch.id(),
+                    origin: position,
+                    position,
+                });
+            }
+        } else if touch_controls.aim_stick.is_none() {
+            touch_controls.aim_stick = Some(VirtualStick {
+                touch_id: touch.id(),
+                origin: position,
+                position,
+            });
+        }
+    }
+
+    for stick in [
+        &mut touch_controls.move_stick,
+        &mut touch_controls.aim_stick,
+    ] {
+        match stick.as_ref().and_then(|s| touches.get_pressed(s.touch_id)) {
+            Some(touch) => {
+                if let Some(stick) = stick {
+                    stick.position = touch.position();
+                }
+            }
+            None => *stick = None,
+        }
+    }
+    touch_controls
+        .buttons
+        .retain(|id, _| touches.get_pressed(*id).is_some());
+}
+
+/// Buttons only show up when they would do something
+pub fn update_touch_buttons(
+    time: Res<SimulationTime>,
+    touch_controls: Res<TouchControls>,
+    temporary: Res<TemporaryItems>,
+    inventory: Res<Inventoty>,
+    player: Query<(&Transform, &Collider), With<Player>>,
+    chests: Query<(&Transform, &Collider, &Chest)>,
+    mut buttons: Query<(&TouchButton, &mut Visibility)>,
+) {
+    let Ok((player, player_collider)) = player.get_single() else {
+        return;
+    };
+    let near_chest = chests.iter().any(|(transform, collider, chest)| {
+        !chest.opened && collider.overlaps(transform, player_collider, player)
+    });
+    for (button, mut visibility) in buttons.iter_mut() {
+        let relevant = match button.0 {
+            GameControl::OpenChest => near_chest,
+            GameControl::SwapWeapon => temporary.offer_active(time.elapsed_seconds_f64()),
+            GameControl::PlaceTurret => inventory.turret.is_some(),
+            GameControl::SaveAndQuit => true,
+            _ => false,
+        };
+        let wanted = if touch_controls.enabled && relevant {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
+
+pub fn draw_virtual_sticks(mut contexts: EguiContexts, touch_controls: Res<TouchControls>) {
+    if !touch_controls.enabled {
+        return;
+    }
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("virtual_sticks"),
+    ));
+    for stick in [&touch_controls.move_stick, &touch_controls.aim_stick]
+        .into_iter()
+        .flatten()
+    {
+        let origin = Pos2::new(stick.origin.x, stick.origin.y);
+        let value = stick.value() * STICK_RADIUS;
+        let knob = Pos2::new(stick.origin.x + value.x, stick.origin.y - value.y);
+        painter.circle_stroke(
+            origin,
+            STICK_RADIUS,
+            Stroke::new(3.0, Color32::from_white_alpha(80)),
+        );
+        painter.circle_filled(knob, STICK_RADIUS / 3.0, Color32::from_white_alpha(120));
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+
+use super::fire_pattern::FirePattern;
+use super::player::{Inventoty, Weapon};
+use super::projectile_modifiers::ProjectileModifiers;
+
+/// Built into the game, used until the file in `assets/` is loaded and when running headless
+const BUILT_IN_ROSTER: &str = include_str!("../../assets/data/arsenal.weapons.ron");
+
+pub struct ArsenalPlugin;
+
+/// This plugin loads the weapon definitions from `assets/data/arsenal.weapons.ron` into [`Arsenal`]
+/// The file is watched, saving it updates the arsenal and the weapons in use while the game runs
+impl Plugin for ArsenalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<WeaponRoster>()
+            .init_asset_loader::<WeaponRosterLoader>()
+            .insert_resource(Arsenal::built_in())
+            .add_systems(Update, apply_roster);
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    /// Never found in chests, e.g. the starting weapon
+    Never,
+}
+
+impl Rarity {
+    fn weight(self) -> f32 {
+        match self {
+            Rarity::Common => 6.0,
+            Rarity::Uncommon => 3.0,
+            Rarity::Rare => 1.0,
+            Rarity::Never => 0.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WeaponDefinition {
+    pub name: String,
+    pub pattern: FirePattern,
+    /// Seconds between shots
+    pub cooldown: f32,
+    pub damage: f32,
+    pub speed: f32,
+    pub lifetime: f32,
+    pub scale: f32,
+    #[serde(default)]
+    pub modifiers: ProjectileModifiers,
+    pub energy_cost: i32,
+    pub rarity: Rarity,
+}
+
+impl WeaponDefinition {
+    pub fn weapon(&self) -> Weapon {
+        Weapon {
+            cd: self.cooldown,
+            from_shot: 0.0,
+            pattern: self.pattern,
+            name: self.name.clone(),
+            damage: self.damage,
+            speed: self.speed,
+            lifetime: self.lifetime,
+            scale: self.scale,
+            modifiers: self.modifiers,
+            energy_cost: self.energy_cost,
+        }
+    }
+}
+
+/// Contents of a `*.weapons.ron` file
+#[derive(Deserialize, TypeUuid, TypePath, Clone, Debug)]
+#[uuid = "93015600-d1eb-4b1a-b07c-7c23e4cb20a4"]
+pub struct WeaponRoster {
+    pub starting_weapon: String,
+    pub starting_turret: String,
+    pub weapons: Vec<WeaponDefinition>,
+}
+
+#[derive(Default)]
+pub struct WeaponRosterLoader;
+
+impl AssetLoader for WeaponRosterLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let roster: WeaponRoster = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(roster));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapons.ron"]
+    }
+}
+
+/// The weapons in play, built from the last valid [`WeaponRoster`]
+#[derive(Resource, Clone)]
+pub struct Arsenal {
+    pub starting_weapon: Weapon,
+    pub starting_turret: Weapon,
+    weapons: Vec<(Weapon, Rarity)>,
+}
+
+impl Arsenal {
+    pub fn built_in() -> Self {
+        let roster: WeaponRoster = ron::from_str(BUILT_IN_ROSTER).expect("built-in weapon roster doesn't parse");
+        Self::from_roster(&roster).expect("built-in weapon roster is invalid")
+    }
+
+    pub fn from_roster(roster: &WeaponRoster) -> Result<Self, String> {
+        let find = |name: &str| {
+            roster
+                .weapons
+                .iter()
+                .find(|w| w.name == name)
+                .map(WeaponDefinition::weapon)
+                .ok_or_else(|| format!("no weapon named {:?}", name))
+        };
+        let weapons: Vec<(Weapon, Rarity)> = roster.weapons.iter().map(|w| (w.weapon(), w.rarity)).collect();
+        if weapons.iter().all(|(_, rarity)| *rarity == Rarity::Never) {
+            return Err("no weapon can be found in chests".into());
+        }
+        Ok(Self {
+            starting_weapon: find(&roster.starting_weapon)?,
+            starting_turret: find(&roster.starting_turret)?,
+            weapons,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Weapon> {
+        self.weapons.iter().map(|(weapon, _)| weapon).find(|w| w.name == name)
+    }
+
+    /// The weapon named `name`, or `fallback` if it was renamed or removed since it was stored
+    pub fn get_or(&self, name: &str, fallback: &Weapon) -> Weapon {
+        match self.get(name) {
+            Some(weapon) => weapon.clone(),
+            None => {
+                warn!("Weapon {:?} isn't in the arsenal anymore, using {:?}", name, fallback.name);
+                fallback.clone()
+            }
+        }
+    }
+
+    /// A weapon for a chest, rarer weapons come up less often
+    pub fn random_drop(&self, rng: &mut impl Rng) -> &Weapon {
+        let (weapon, _) = self
+            .weapons
+            .choose_weighted(rng, |(_, rarity)| rarity.weight())
+            .expect("the arsenal has droppable weapons");
+        weapon
+    }
+}
+
+/// Rebuilds the [`Arsenal`] when the roster is loaded or changed on disk.
+/// Weapons already in use get the new stats too, they are matched by name.
+fn apply_roster(
+    mut events: EventReader<AssetEvent<WeaponRoster>>,
+    rosters: Res<Assets<WeaponRoster>>,
+    mut arsenal: ResMut<Arsenal>,
+    mut inventory: ResMut<Inventoty>,
+    mut weapons: Query<&mut Weapon>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let Some(roster) = rosters.get(handle) else {
+            continue;
+        };
+        match Arsenal::from_roster(roster) {
+            Ok(new_arsenal) => {
+                info!("Weapon roster loaded, {} weapons", roster.weapons.len());
+                *arsenal = new_arsenal;
+                let refresh = |weapon: &mut Weapon| {
+                    if let Some(updated) = arsenal.get(&weapon.name) {
+                        *weapon = Weapon { from_shot: weapon.from_shot, ..updated.clone() };
+                    }
+                };
+                for mut weapon in weapons.iter_mut() {
+                    refresh(&mut weapon);
+                }
+                if let Some(turret) = inventory.turret.as_mut() {
+                    refresh(turret);
+                }
+            }
+            Err(e) => warn!("Weapon roster ignored: {}", e),
+        }
+    }
+}
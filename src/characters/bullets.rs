@@ -1,88 +1,268 @@
 use bevy::{prelude::*, math::vec3};
 use bevy::sprite::SpriteBundle;
 
-use crate::{loading::TextureAssets, GameState};
+use crate::{cli::has_flag, collision::Collider, game_events::BulletLanded, loading::TextureAssets, simulation::{Interpolated, SimulationSet, SimulationTime}, spatial_grid::SpatialGrid, GameState};
 
-use super::{enemy::Enemy, base_character::Hp};
+use super::{base_character::Hp, damage::DamageEvent, faction::{Faction, FriendlyFire}, fire_pattern::fire_bursts, player::{Player, Weapon}, projectile_modifiers::{steer, ProjectileModifiers}, status_effects::StatusEvent};
 
 pub struct BulletPlugin;
 
 impl Plugin for BulletPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_systems(Update, ((move_bullets, check_collisions).chain()).run_if(in_state(GameState::Playing)));
+        app.insert_resource(FriendlyFire(has_flag("--friendly-fire")))
+            .init_resource::<BulletPool>()
+            .add_systems(FixedUpdate, (fire_bursts, move_bullets, check_collisions)
+            .chain()
+            .in_set(SimulationSet::Bullets)
+            .run_if(in_state(GameState::Playing)));
     }
 }
 
+/// Bullets are put back in the pool once they are this far outside the camera's view
+pub const BULLET_DESPAWN_MARGIN: f32 = 200.0;
+/// Without a view, e.g. headless, bullets are put back in the pool this far from the player instead
+pub const BULLET_DESPAWN_DISTANCE: f32 = 800.0;
 
+/// Hit radius of a bullet, in pixels of the bullet texture
+const BULLET_RADIUS: f32 = 16.0;
+/// Homing bullets look for targets this close
+const HOMING_RANGE: f32 = 300.0;
+/// Ricocheting bullets bounce to targets this close
+const RICOCHET_RANGE: f32 = 250.0;
+/// Push of a bullet hit, in pixels per second
+const BULLET_KNOCKBACK: f32 = 120.0;
+/// Push of an explosion, in pixels per second
+const EXPLOSION_KNOCKBACK: f32 = 240.0;
 
 #[derive(Component)]
 pub struct Bullet {
     damage: f32,
-    speed: f32, 
-    time_alive: f32
+    speed: f32,
+    time_alive: f32,
+    lifetime: f32,
+    faction: Faction,
+    /// Whoever fired it, never hit by it
+    owner: Entity,
+    /// Name of the weapon that fired it, for kill credit
+    weapon: String,
+    /// Pierce and ricochet count down as they are used
+    modifiers: ProjectileModifiers,
+    /// Targets already hit, a bullet hurts each of them once
+    hits: Vec<Entity>,
 }
 
 #[derive(Component)]
 pub struct BulletVelocity(Vec3);
 
+/// Marks a bullet waiting in the [`BulletPool`], hidden and ignored by the bullet systems
+#[derive(Component)]
+pub struct Pooled;
+
+/// Bullets that are done, reused by [`shot_bullet`] instead of spawning new entities
+#[derive(Resource, Default)]
+pub struct BulletPool(Vec<Entity>);
 
 pub fn shot_bullet(
     commands: &mut Commands,
     textures: &Res<TextureAssets>,
+    pool: &mut BulletPool,
+    owner: Entity,
+    faction: Faction,
     pos: Vec3,
     velocity: Vec3,
-    damage: f32,
-    speed: f32
+    weapon: &Weapon,
 ) {
-    commands.spawn( SpriteBundle {
-        texture: textures.texture_bullet.clone(),
-        transform: Transform {
-            translation: pos + vec3(0.,0.,0.3),
-            scale: Vec3::splat(0.25),
-            ..Default::default()
-        },
+    let translation = pos + vec3(0.,0.,0.3);
+    let transform = Transform {
+        translation,
+        scale: Vec3::splat(weapon.scale),
         ..Default::default()
-    })
-    .insert(Bullet {
-        damage,
-        speed,
-        time_alive: 0.0
-    })
-    .insert(BulletVelocity(velocity));
+    };
+    let bullet = Bullet {
+        damage: weapon.damage,
+        speed: weapon.speed,
+        time_alive: 0.0,
+        lifetime: weapon.lifetime,
+        faction,
+        owner,
+        weapon: weapon.name.clone(),
+        modifiers: weapon.modifiers,
+        hits: Vec::new(),
+    };
+    let mut entity = match pool.0.pop() {
+        Some(entity) => {
+            let mut entity = commands.entity(entity);
+            entity.remove::<Pooled>().insert((transform, Visibility::Inherited));
+            entity
+        }
+        None => commands.spawn(SpriteBundle {
+            texture: textures.texture_bullet.clone(),
+            transform,
+            ..Default::default()
+        }),
+    };
+    entity.insert((
+        bullet,
+        Collider::circle(BULLET_RADIUS),
+        BulletVelocity(velocity),
+        Interpolated::new(translation),
+    ));
 }
 
+/// Hides the bullet and hands it back to the pool
+fn release_bullet(commands: &mut Commands, pool: &mut BulletPool, entity: Entity) {
+    commands.entity(entity).remove::<Collider>().insert((Pooled, Visibility::Hidden));
+    pool.0.push(entity);
+}
+
+/// The closest target `bullet` can still hurt within `range` of `position`.
+/// `lookup` gives the position and faction of an entity of the grid, if it's a target at all.
+fn closest_target(
+    grid: &SpatialGrid,
+    friendly_fire: &FriendlyFire,
+    bullet: &Bullet,
+    position: Vec2,
+    range: f32,
+    lookup: impl Fn(Entity) -> Option<(Vec2, Faction)>,
+) -> Option<(Entity, Vec2)> {
+    grid.near(position, range)
+        .filter(|candidate| !bullet.hits.contains(candidate))
+        .filter_map(|candidate| lookup(candidate).map(|(at, faction)| (candidate, at, faction)))
+        .filter(|(candidate, at, faction)| {
+            at.distance_squared(position) <= range * range
+                && bullet.faction.hurts(bullet.owner, *candidate, *faction, friendly_fire)
+        })
+        .min_by(|(_, a, _), (_, b, _)| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+        .map(|(candidate, at, _)| (candidate, at))
+}
 
 fn move_bullets(
-    time: Res<Time>,
-    mut query: Query<(&mut Transform, &mut Bullet, &BulletVelocity)>,
+    time: Res<SimulationTime>,
+    friendly_fire: Res<FriendlyFire>,
+    grid: Res<SpatialGrid>,
+    mut query: Query<(&mut Transform, &mut Bullet, &mut BulletVelocity), Without<Pooled>>,
+    query_targets: Query<(&Transform, &Faction), (With<Hp>, Without<Bullet>)>,
 ) {
-
-    for (mut tr, mut bullet, velocity) in query.iter_mut() {
+    let lookup = |entity| query_targets.get(entity).ok().map(|(t, faction)| (t.translation.truncate(), *faction));
+    for (mut tr, mut bullet, mut velocity) in query.iter_mut() {
+        if bullet.modifiers.homing > 0.0 {
+            let position = tr.translation.truncate();
+            if let Some((_, target)) = closest_target(&grid, &friendly_fire, &bullet, position, HOMING_RANGE, lookup) {
+                let max_angle = bullet.modifiers.homing.to_radians() * time.delta_seconds();
+                let direction = steer(velocity.0.truncate(), (target - position).normalize_or_zero(), max_angle);
+                velocity.0 = direction.extend(0.0);
+            }
+        }
         tr.translation += velocity.0 * time.delta_seconds() * bullet.speed;
         bullet.time_alive += time.delta_seconds();
     }
 }
 
+/// Where bullets may fly: the camera's view and [`BULLET_DESPAWN_MARGIN`] around it.
+/// Headless the camera has no viewport, so it's a square of [`BULLET_DESPAWN_DISTANCE`] around the player.
+fn bullet_area(
+    query_camera: &Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<Camera2d>>,
+    query_player: &Query<&Transform, With<Player>>,
+) -> Option<Rect> {
+    let view = query_camera.iter().find_map(|(camera, transform, projection)| {
+        let size = camera.logical_viewport_size()?;
+        let half_size = size * projection.scale / 2.0 + Vec2::splat(BULLET_DESPAWN_MARGIN);
+        Some(Rect::from_center_half_size(transform.translation().truncate(), half_size))
+    });
+    view.or_else(|| {
+        let player = query_player.get_single().ok()?.translation.truncate();
+        Some(Rect::from_center_half_size(player, Vec2::splat(BULLET_DESPAWN_DISTANCE)))
+    })
+}
+
+/// Bullets hit everything with `Hp` their faction hurts, see [`Faction::hurts`], one target per step.
+/// After a hit the bullet pierces through, else ricochets to another target, else stops.
 fn check_collisions(
     mut commands: Commands,
-    query: Query<(Entity, &Transform, &Bullet)>,
-    mut query_enemies: Query<(&mut Hp, &Transform), With<Enemy>>
+    mut pool: ResMut<BulletPool>,
+    friendly_fire: Res<FriendlyFire>,
+    grid: Res<SpatialGrid>,
+    mut query: Query<(Entity, &Transform, &Collider, &mut Bullet, &mut BulletVelocity), Without<Pooled>>,
+    query_targets: Query<(Entity, &Transform, &Collider, &Faction), (With<Hp>, Without<Bullet>)>,
+    query_player: Query<&Transform, With<Player>>,
+    query_camera: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<Camera2d>>,
+    mut damage: EventWriter<DamageEvent>,
+    mut status_events: EventWriter<StatusEvent>,
+    mut landed: EventWriter<BulletLanded>,
 ) {
-    for (entity, bullet_transform, bullet) in query.iter() {
-        if bullet.time_alive >= 5.0 {
-            if let Some(ent) = commands.get_entity(entity){
-                ent.despawn_recursive();
-            }
+    let area = bullet_area(&query_camera, &query_player);
+    for (entity, bullet_transform, bullet_collider, mut bullet, mut velocity) in query.iter_mut() {
+        let position = bullet_transform.translation.truncate();
+        let far = area.is_some_and(|area| !area.contains(position));
+        if bullet.time_alive >= bullet.lifetime || far {
+            release_bullet(&mut commands, &mut pool, entity);
+            continue;
+        }
+
+        let hit = grid.near(position, bullet_collider.extent(bullet_transform)).find(|candidate| {
+            let Ok((target, target_transform, target_collider, faction)) = query_targets.get(*candidate) else {
+                return false;
+            };
+            !bullet.hits.contains(&target)
+                && bullet.faction.hurts(bullet.owner, target, *faction, &friendly_fire)
+                && bullet_collider.overlaps(bullet_transform, target_collider, target_transform)
+        });
+        let Some(hit) = hit else {
             continue;
+        };
+        let from = position - velocity.0.truncate();
+        damage.send(
+            DamageEvent::hit(hit, bullet.damage)
+                .with_knockback(from, BULLET_KNOCKBACK)
+                .by(bullet.owner, Some(bullet.weapon.clone())),
+        );
+        if bullet.hits.is_empty() {
+            landed.send(BulletLanded { bullet: entity, owner: bullet.owner });
         }
-        for (mut enemy_hp, enemy_transform) in query_enemies.iter_mut() {
-            if bullet_transform.translation.distance_squared(enemy_transform.translation) <= 25.0*25.0 {
-                enemy_hp.0 -= bullet.damage;
-                if let Some(ent) = commands.get_entity(entity){
-                    ent.despawn_recursive();
+        bullet.hits.push(hit);
+        if let Some(effect) = bullet.modifiers.status {
+            status_events.send(StatusEvent {
+                target: hit,
+                effect,
+                by: Some(bullet.owner),
+                weapon: Some(bullet.weapon.clone()),
+            });
+        }
+
+        if let Some(explosion) = bullet.modifiers.explosion {
+            let blast = Collider::circle(explosion.radius);
+            let blast_transform = Transform::from_translation(bullet_transform.translation);
+            for candidate in grid.near(position, explosion.radius) {
+                if candidate == hit {
+                    continue;
+                }
+                let Ok((target, target_transform, target_collider, faction)) = query_targets.get(candidate) else {
+                    continue;
+                };
+                if bullet.faction.hurts(bullet.owner, target, *faction, &friendly_fire)
+                    && blast.overlaps(&blast_transform, target_collider, target_transform)
+                {
+                    damage.send(
+                        DamageEvent::hit(target, explosion.damage)
+                            .with_knockback(position, EXPLOSION_KNOCKBACK)
+                            .by(bullet.owner, Some(bullet.weapon.clone())),
+                    );
                 }
             }
         }
+
+        if bullet.modifiers.pierce > 0 {
+            bullet.modifiers.pierce -= 1;
+            continue;
+        }
+        if bullet.modifiers.ricochet > 0 {
+            let lookup = |entity| query_targets.get(entity).ok().map(|(_, t, _, faction)| (t.translation.truncate(), *faction));
+            if let Some((_, target)) = closest_target(&grid, &friendly_fire, &bullet, position, RICOCHET_RANGE, lookup) {
+                bullet.modifiers.ricochet -= 1;
+                velocity.0 = (target - position).normalize_or_zero().extend(0.0);
+                continue;
+            }
+        }
+        release_bullet(&mut commands, &mut pool, entity);
     }
 }
-
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 
 use crate::{map::{Map, DayNight}, ui::UiLog, GameState};
 
-use super::{player::{Inventoty, TemporaryItems}};
+use super::{bullets::BulletPool, enemy::SpawnTimer, player::{Inventoty, TemporaryItems}};
 pub struct CleanerPlugin;
 impl Plugin for CleanerPlugin {
     fn build(&self, app: &mut App) {
@@ -27,14 +27,11 @@ fn despawn_all(
     commands.remove_resource::<DayNight>();
 
     commands.insert_resource(Map::default());
-    commands.insert_resource(DayNight {
-        current_day_time: 0.7,
-        current_night_time: 0.3,
-        full_day_time: 60.0,
-        time: 0.0,
-        is_night: false,
-        day: 1
-    });
+    // Every run starts from the same state, so a seed and a replay always reproduce it
+    commands.insert_resource(DayNight::default());
+    commands.insert_resource(SpawnTimer::default());
+    // The pooled bullets were despawned with everything else
+    commands.insert_resource(BulletPool::default());
     commands.init_resource::<UiLog>();
     commands.init_resource::<Inventoty>();
     commands.init_resource::<TemporaryItems>();
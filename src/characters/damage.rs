@@ -0,0 +1,204 @@
+use bevy::ecs::query::Has;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game_events::PlayerDamaged;
+use crate::simulation::{SimulationSet, SimulationTime};
+use crate::GameState;
+
+use super::base_character::Hp;
+use super::player::Player;
+
+/// Speed, in pixels per second, a knockback loses every second
+const KNOCKBACK_FRICTION: f32 = 600.0;
+
+pub struct DamagePlugin;
+
+/// This plugin is the only place `Hp` goes down: every damage source sends a [`DamageEvent`],
+/// which goes through [`Invulnerable`], [`Shield`] and [`Armour`] before it reaches `Hp`
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_systems(FixedUpdate, (tick_invulnerability, regenerate_shields, apply_damage, apply_knockback)
+                .chain()
+                .in_set(SimulationSet::Damage)
+                .run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DamageKind {
+    /// Projectiles, explosions and contact, blocked by armour and invulnerability
+    Hit,
+    /// Status effects, only shields stop it
+    OverTime,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageKind,
+    /// Where the hit came from, the target is pushed away from it
+    pub from: Option<Vec2>,
+    /// Speed of the push, in pixels per second
+    pub knockback: f32,
+    /// Who dealt it
+    pub by: Option<Entity>,
+    /// Name of the weapon it was dealt with
+    pub weapon: Option<String>,
+}
+
+impl DamageEvent {
+    pub fn hit(target: Entity, amount: f32) -> Self {
+        Self { target, amount, kind: DamageKind::Hit, from: None, knockback: 0.0, by: None, weapon: None }
+    }
+
+    pub fn over_time(target: Entity, amount: f32) -> Self {
+        Self { target, amount, kind: DamageKind::OverTime, from: None, knockback: 0.0, by: None, weapon: None }
+    }
+
+    pub fn with_knockback(self, from: Vec2, knockback: f32) -> Self {
+        Self { from: Some(from), knockback, ..self }
+    }
+
+    pub fn by(self, by: Entity, weapon: Option<String>) -> Self {
+        Self { by: Some(by), weapon, ..self }
+    }
+}
+
+/// Source of the last damage an entity took, for kill credit
+#[derive(Component, Default)]
+pub struct LastHit {
+    pub by: Option<Entity>,
+    pub weapon: Option<String>,
+}
+
+/// What `Hp` starts at, for health bars
+#[derive(Component)]
+pub struct MaxHp(pub f32);
+
+/// Share of hit damage blocked, from 0 to 1
+#[derive(Component)]
+pub struct Armour(pub f32);
+
+/// Soaks damage before `Hp` and refills after a while without damage
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Shield {
+    pub value: f32,
+    pub max: f32,
+    /// Points per second
+    pub regen: f32,
+    /// Seconds without damage before it refills
+    pub regen_delay: f32,
+    since_damage: f32,
+}
+
+impl Shield {
+    pub fn new(max: f32, regen: f32, regen_delay: f32) -> Self {
+        Self { value: max, max, regen, regen_delay, since_damage: 0.0 }
+    }
+}
+
+/// Hits are ignored for `duration` seconds after one lands
+#[derive(Component)]
+pub struct Invulnerable {
+    pub duration: f32,
+    remaining: f32,
+}
+
+impl Invulnerable {
+    pub fn after_hit(duration: f32) -> Self {
+        Self { duration, remaining: 0.0 }
+    }
+
+    pub fn active(&self) -> bool {
+        self.remaining > 0.0
+    }
+}
+
+/// Push from the last hits, in pixels per second, slowing down by itself
+#[derive(Component, Default)]
+pub struct Knockback(pub Vec2);
+
+fn tick_invulnerability(time: Res<SimulationTime>, mut query: Query<&mut Invulnerable>) {
+    for mut invulnerable in query.iter_mut() {
+        if invulnerable.active() {
+            invulnerable.remaining -= time.delta_seconds();
+        }
+    }
+}
+
+fn regenerate_shields(time: Res<SimulationTime>, mut query: Query<&mut Shield>) {
+    for mut shield in query.iter_mut() {
+        shield.since_damage += time.delta_seconds();
+        if shield.since_damage >= shield.regen_delay && shield.value < shield.max {
+            shield.value = (shield.value + shield.regen * time.delta_seconds()).min(shield.max);
+        }
+    }
+}
+
+fn apply_damage(
+    mut events: EventReader<DamageEvent>,
+    mut player_damaged: EventWriter<PlayerDamaged>,
+    mut query: Query<(
+        &mut Hp,
+        &Transform,
+        Option<&Armour>,
+        Option<&mut Shield>,
+        Option<&mut Invulnerable>,
+        Option<&mut Knockback>,
+        Option<&mut LastHit>,
+        Has<Player>,
+    )>,
+) {
+    for event in events.iter() {
+        let Ok((mut hp, transform, armour, shield, invulnerable, knockback, last_hit, is_player)) = query.get_mut(event.target) else {
+            continue;
+        };
+        let mut amount = event.amount;
+        if event.kind == DamageKind::Hit {
+            if let Some(mut invulnerable) = invulnerable {
+                if invulnerable.active() {
+                    continue;
+                }
+                invulnerable.remaining = invulnerable.duration;
+            }
+            if let Some(armour) = armour {
+                amount *= 1.0 - armour.0.clamp(0.0, 1.0);
+            }
+            if let (Some(from), Some(mut knockback)) = (event.from, knockback) {
+                let away = (transform.translation.truncate() - from).normalize_or_zero();
+                knockback.0 += away * event.knockback;
+            }
+        }
+        let mut absorbed = 0.0;
+        if let Some(mut shield) = shield {
+            shield.since_damage = 0.0;
+            absorbed = amount.min(shield.value);
+            shield.value -= absorbed;
+            amount -= absorbed;
+        }
+        if amount > 0.0 {
+            hp.0 -= amount;
+        }
+        if let Some(mut last_hit) = last_hit {
+            last_hit.by = event.by;
+            last_hit.weapon = event.weapon.clone();
+        }
+        if is_player && amount + absorbed > 0.0 {
+            player_damaged.send(PlayerDamaged { amount, absorbed, hp_left: hp.0 });
+        }
+    }
+}
+
+fn apply_knockback(time: Res<SimulationTime>, mut query: Query<(&mut Transform, &mut Knockback)>) {
+    for (mut transform, mut knockback) in query.iter_mut() {
+        if knockback.0 == Vec2::ZERO {
+            continue;
+        }
+        transform.translation += (knockback.0 * time.delta_seconds()).extend(0.0);
+        let speed = (knockback.0.length() - KNOCKBACK_FRICTION * time.delta_seconds()).max(0.0);
+        knockback.0 = knockback.0.normalize_or_zero() * speed;
+    }
+}
@@ -5,81 +5,153 @@ use crate::GameState;
 use crate::characters::base_character::{AnimationIndices, AnimationTimer, Hp};
 use crate::characters::player::Player;
 use crate::map::DayNight;
+use crate::collision::Collider;
+use crate::game_events::EnemyKilled;
+use crate::rng::GameRng;
+use crate::spatial_grid::{update_spatial_grid, SpatialGrid};
+use crate::simulation::{Interpolated, SimulationSet, SimulationTime};
 use bevy::math::vec3;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::damage::{Armour, DamageEvent, Knockback, LastHit, MaxHp};
+use super::faction::Faction;
+use super::status_effects::{speed_multiplier, StatusEffects, StatusEvent};
+use super::enemy_kinds::{insert_behaviour, move_chargers, move_spitters, Chaser, EnemyKind};
 
 
 pub struct EnemyPlugin;
 
+/// Enemies fleeing at day are removed this far from the player, well outside the view
+pub const FLEE_DESPAWN_DISTANCE: f32 = 800.0;
+/// Push of an enemy touching the player, in pixels per second
+const CONTACT_KNOCKBACK: f32 = 300.0;
+
 #[derive(Resource)]
 pub struct SpawnTimer {
     /// How often to spawn a new bomb? (repeating timer)
     timer: Timer,
 }
 
+impl Default for SpawnTimer {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(1.0, TimerMode::Repeating)
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Enemy {
-    speed: f32
+    pub speed: f32,
+    pub kind: EnemyKind,
 }
 
+/// One sprite atlas per [`EnemyKind`], shared by all the enemies of that kind
+#[derive(Resource)]
+pub struct EnemyAtlases(HashMap<EnemyKind, Handle<TextureAtlas>>);
+
 /// This plugin handles Enemy related stuff like movement
 /// Enemy logic is only active during the State `GameState::Playing`
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app
-        .insert_resource(SpawnTimer {
-            timer: Timer::from_seconds(1.0, TimerMode::Repeating)
-        })
-        //.add_systems(OnEnter(GameState::Playing), spawn_enemy)
-        .add_systems(Update, (move_enemy, spawn_enemy_timeout, check_death, check_collisions).run_if(in_state(GameState::Playing)))
+        .init_resource::<SpawnTimer>()
+        .add_systems(OnEnter(GameState::Playing), build_enemy_atlases.run_if(not(resource_exists::<EnemyAtlases>())))
+        .add_systems(FixedUpdate, (spawn_enemy_timeout, move_enemy, move_chargers, move_spitters, check_death)
+            .chain()
+            .in_set(SimulationSet::Enemies)
+            .run_if(in_state(GameState::Playing)))
+        .add_systems(FixedUpdate, check_collisions
+            .after(update_spatial_grid)
+            .in_set(SimulationSet::Collisions)
+            .run_if(in_state(GameState::Playing)))
         ;
     }
 }
 
 
+pub fn build_enemy_atlases(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let atlases = EnemyKind::ALL
+        .iter()
+        .map(|kind| (*kind, texture_atlases.add(kind.atlas(&textures))))
+        .collect();
+    commands.insert_resource(EnemyAtlases(atlases));
+}
+
 fn spawn_enemy(commands: &mut Commands, 
-    textures: &Res<TextureAssets>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>, 
+    atlases: &EnemyAtlases,
+    rng: &mut GameRng,
+    kind: EnemyKind,
     pos: Vec3,
     strength: f32
 ) {
+    let true_strength = (strength + (rng.enemies.gen::<f32>() - 0.5) * 0.3).clamp(0.2, 2.0);
+    let speed = kind.speed() + rng.enemies.gen::<f32>()*20.0;
+    insert_enemy(commands, atlases, kind, pos, true_strength, speed);
+}
 
-    let texture_atlas =
-        TextureAtlas::from_grid(textures.texture_enemy.clone(), Vec2::new(200.0, 200.0), 4, 4, None, None);
-    let texture_atlas_enemy_handle = texture_atlases.add(texture_atlas);
-    let animation_indices = AnimationIndices { first: 0, last: 15 };
-    let true_strength = (strength + (rand::random::<f32>() - 0.5) * 0.3).clamp(0.2, 2.0);
-    commands.spawn( (
+/// An enemy of `kind` at full health, its size and `Hp` grow with `strength`.
+/// Saved enemies are restored through it too.
+pub fn insert_enemy(commands: &mut Commands,
+    atlases: &EnemyAtlases,
+    kind: EnemyKind,
+    pos: Vec3,
+    true_strength: f32,
+    speed: f32,
+) -> Entity {
+    let animation_indices = AnimationIndices { first: 0, last: kind.frame_count() - 1 };
+    let sprite = TextureAtlasSprite::new(animation_indices.first);
+    let mut entity = commands.spawn( (
         SpriteSheetBundle {
-            texture_atlas: texture_atlas_enemy_handle,
-            sprite: TextureAtlasSprite::new(animation_indices.first),
+            texture_atlas: atlases.0[&kind].clone(),
+            sprite,
             transform:Transform{
                 translation: pos,
-                scale: Vec3::splat(0.25 * true_strength),
+                scale: Vec3::splat(kind.scale() * true_strength),
                 ..Default::default()
             },
             ..default()
         },
         animation_indices,
         AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-    ))
-    .insert(Enemy { speed : 120.0 + rand::random::<f32>()*20.0 })
-    .insert(Hp(5.0 + 10.0 * true_strength));
-
+    ));
+    entity
+    .insert(Interpolated::new(pos))
+    .insert(Enemy { speed, kind })
+    .insert(Hp(kind.hp(true_strength)))
+    .insert(MaxHp(kind.hp(true_strength)))
+    .insert(Armour(kind.armour()))
+    .insert(Knockback::default())
+    .insert(LastHit::default())
+    .insert(Faction::Enemies)
+    .insert(Collider::circle(80.0))
+    .insert(StatusEffects::default());
+    insert_behaviour(&mut entity, kind);
+    entity.id()
 }
 
 fn move_enemy(
     mut commands: Commands,
     day_night: Res<DayNight>,
-    time: Res<Time>,
-    mut enemy_query: Query<(&mut Transform, &Enemy, Entity, &ComputedVisibility), Without<Player>>,
+    time: Res<SimulationTime>,
+    mut enemy_query: Query<(&mut Transform, &Enemy, Entity, Option<&Chaser>, Option<&StatusEffects>), Without<Player>>,
     player_query: Query<&Transform, (Without<Enemy>, With<Player>)>,
 
 ) {
     let player = player_query.single();
     
-    for (mut tr, enemy, entity, visibility) in enemy_query.iter_mut(){
-        
+    for (mut tr, enemy, entity, chaser, effects) in enemy_query.iter_mut(){
+        // At night only chasers walk straight in, the other kinds have their own systems
+        if day_night.is_night && chaser.is_none() {
+            continue;
+        }
         let mut direction = player.translation - tr.translation;
         if !day_night.is_night {
             direction = -direction;
@@ -89,13 +161,14 @@ fn move_enemy(
         direction.z = 0.0;
         direction = direction.normalize();
 
-        let speed = enemy.speed;
+        let speed = enemy.speed * speed_multiplier(effects);
         let movement = direction * speed * time.delta_seconds();
         tr.translation += movement;
         
         
         if !day_night.is_night {
-            if !visibility.is_visible_in_view() {
+            // Distance instead of visibility, so it works the same without rendering
+            if tr.translation.truncate().distance_squared(player.translation.truncate()) > FLEE_DESPAWN_DISTANCE * FLEE_DESPAWN_DISTANCE {
                 if let Some(ent) = commands.get_entity(entity) {
                     ent.despawn_recursive();
                     info!("Despawn fleed enemy");
@@ -110,13 +183,12 @@ fn move_enemy(
 
 fn spawn_enemy_timeout(
     mut timer: ResMut<SpawnTimer>,
-    time: Res<Time>,
+    time: Res<SimulationTime>,
     mut commands: Commands,
     day_night: Res<DayNight>,
     player_query: Query<&Transform, (Without<Enemy>, With<Player>)>,
-    textures: Res<TextureAssets>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>
-
+    atlases: Res<EnemyAtlases>,
+    mut rng: ResMut<GameRng>,
 ){
     if day_night.is_night {
         timer.timer.tick(time.delta());
@@ -124,11 +196,22 @@ fn spawn_enemy_timeout(
             let base_pos = player_query.single().translation;
             let count = (day_night.current_night_time * 10.0).round() as i32;
             for _ in 0..count {
-                let distance = 600.0 + rand::random::<f32>() * 600.0;
-                let angle = PI * 2.0 * rand::random::<f32>();
+                let distance = 600.0 + rng.enemies.gen::<f32>() * 600.0;
+                let angle = PI * 2.0 * rng.enemies.gen::<f32>();
                 let pos = vec3(angle.cos() * distance, angle.sin() * distance, 0.05) + base_pos;
                 let strength = (day_night.day as f32 / 10.0) + 0.5;
-                spawn_enemy(&mut commands, &textures, &mut texture_atlases, pos, strength);
+                let kind = *EnemyKind::ALL
+                    .choose_weighted(&mut rng.enemies, |kind| kind.spawn_weight(day_night.day))
+                    .expect("grunts spawn every night");
+                let pack = rng.enemies.gen_range(kind.pack_size());
+                for _ in 0..pack {
+                    let offset = if pack > 1 {
+                        vec3(rng.enemies.gen_range(-40.0..40.0), rng.enemies.gen_range(-40.0..40.0), 0.0)
+                    } else {
+                        Vec3::ZERO
+                    };
+                    spawn_enemy(&mut commands, &atlases, &mut rng, kind, pos + offset, strength);
+                }
             }
         }
     }
@@ -136,25 +219,48 @@ fn spawn_enemy_timeout(
 
 fn check_death(
     mut commands: Commands,
-    query: Query<(&Hp, Entity), (Changed<Hp>, With<Enemy>)>
+    mut killed: EventWriter<EnemyKilled>,
+    query: Query<(&Hp, Entity, &Enemy, &Transform, &LastHit), Changed<Hp>>
 ) {
-    for (hp, entity) in query.iter() {
+    for (hp, entity, enemy, transform, last_hit) in query.iter() {
         if hp.0 <= 0.0 {
+            killed.send(EnemyKilled {
+                enemy: entity,
+                kind: enemy.kind,
+                position: transform.translation.truncate(),
+                killer: last_hit.by,
+                weapon: last_hit.weapon.clone(),
+            });
             commands.entity(entity).despawn_recursive();
         }
     }
 }
 
 fn check_collisions(
-    time: Res<Time>,
-    query: Query<&Transform, With<Enemy>>,
-    mut query_player: Query<(&mut Hp, &Transform), With<Player>>
+    grid: Res<SpatialGrid>,
+    query: Query<(Entity, &Transform, &Collider, &Enemy, Option<&StatusEffects>)>,
+    query_player: Query<(Entity, &Transform, &Collider), With<Player>>,
+    mut damage: EventWriter<DamageEvent>,
+    mut status_events: EventWriter<StatusEvent>,
 ) {
-    let (mut hp, player_tr) = query_player.single_mut();
+    let (player, player_tr, player_collider) = query_player.single();
 
-    for transform in query.iter() {
-            if transform.translation.distance_squared(player_tr.translation) <= 50. * 50. {
-                hp.0 -= 2.0 * time.delta_seconds();
+    for entity in grid.near(player_tr.translation.truncate(), player_collider.extent(player_tr)) {
+        let Ok((entity, transform, collider, enemy, effects)) = query.get(entity) else {
+            continue;
+        };
+        if effects.is_some_and(StatusEffects::frozen) {
+            continue;
+        }
+        if collider.overlaps(transform, player_collider, player_tr) {
+            damage.send(
+                DamageEvent::hit(player, enemy.kind.contact_damage())
+                    .with_knockback(transform.translation.truncate(), CONTACT_KNOCKBACK)
+                    .by(entity, None),
+            );
+            if let Some(effect) = enemy.kind.contact_effect() {
+                status_events.send(StatusEvent { target: player, effect, by: Some(entity), weapon: None });
             }
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,356 @@
+use std::ops::RangeInclusive;
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::loading::TextureAssets;
+use crate::map::DayNight;
+use crate::simulation::SimulationTime;
+
+use super::bullets::BulletPool;
+use super::enemy::Enemy;
+use super::faction::Faction;
+use super::fire_pattern::{fire_weapon, FirePattern};
+use super::player::{Player, Weapon, PLAYER_INVULNERABILITY};
+use super::projectile_modifiers::ProjectileModifiers;
+use super::status_effects::{speed_multiplier, Stacking, StatusEffect, StatusEffects, StatusKind};
+
+/// Chargers start winding up this close to the player
+const CHARGE_RANGE: f32 = 250.0;
+const CHARGE_WIND_UP: f32 = 0.6;
+const CHARGE_DASH: f32 = 0.5;
+const CHARGE_RECOVER: f32 = 0.8;
+const CHARGE_SPEED: f32 = 450.0;
+
+/// Spitters try to stay this far from the player, give or take [`SPIT_DISTANCE_TOLERANCE`]
+const SPIT_DISTANCE: f32 = 300.0;
+const SPIT_DISTANCE_TOLERANCE: f32 = 50.0;
+const SPIT_RANGE: f32 = 450.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum EnemyKind {
+    /// Walks straight at the player
+    Grunt,
+    /// Winds up and dashes at the player
+    Charger,
+    /// Keeps its distance and spits projectiles
+    Spitter,
+    /// Weak, fast, comes in packs
+    Swarmer,
+    /// Slow and hard to kill
+    Tank,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 5] = [
+        EnemyKind::Grunt,
+        EnemyKind::Charger,
+        EnemyKind::Spitter,
+        EnemyKind::Swarmer,
+        EnemyKind::Tank,
+    ];
+
+    /// Base walking speed, each enemy gets up to 20 more
+    pub fn speed(self) -> f32 {
+        match self {
+            EnemyKind::Grunt => 120.0,
+            EnemyKind::Charger => 90.0,
+            EnemyKind::Spitter => 100.0,
+            EnemyKind::Swarmer => 160.0,
+            EnemyKind::Tank => 60.0,
+        }
+    }
+
+    /// Hp for a strength, which grows every night
+    pub fn hp(self, strength: f32) -> f32 {
+        match self {
+            EnemyKind::Grunt => 5.0 + 10.0 * strength,
+            EnemyKind::Charger => 4.0 + 8.0 * strength,
+            EnemyKind::Spitter => 3.0 + 6.0 * strength,
+            EnemyKind::Swarmer => 2.0 + 3.0 * strength,
+            EnemyKind::Tank => 20.0 + 30.0 * strength,
+        }
+    }
+
+    /// Sprite scale at strength 1
+    pub fn scale(self) -> f32 {
+        match self {
+            EnemyKind::Grunt => 0.25,
+            EnemyKind::Charger => 0.25,
+            EnemyKind::Spitter => 0.22,
+            EnemyKind::Swarmer => 0.15,
+            EnemyKind::Tank => 0.4,
+        }
+    }
+
+    /// Damage of one hit on the player while touching them. A hit lands once per
+    /// [`PLAYER_INVULNERABILITY`], so this is the damage per second below times that.
+    pub fn contact_damage(self) -> f32 {
+        self.contact_damage_per_second() * PLAYER_INVULNERABILITY
+    }
+
+    /// Damage per second while touching the player
+    fn contact_damage_per_second(self) -> f32 {
+        match self {
+            EnemyKind::Grunt => 2.0,
+            EnemyKind::Charger => 3.0,
+            EnemyKind::Spitter => 1.0,
+            EnemyKind::Swarmer => 1.0,
+            EnemyKind::Tank => 5.0,
+        }
+    }
+
+    /// Share of hit damage blocked, see [`Armour`](super::damage::Armour)
+    pub fn armour(self) -> f32 {
+        match self {
+            EnemyKind::Tank => 0.5,
+            _ => 0.0,
+        }
+    }
+
+    /// How many spawn together
+    pub fn pack_size(self) -> RangeInclusive<u32> {
+        match self {
+            EnemyKind::Swarmer => 4..=6,
+            _ => 1..=1,
+        }
+    }
+
+    /// Relative chance to spawn on a night, kinds show up one after another as the nights go by
+    pub fn spawn_weight(self, day: i32) -> f32 {
+        let weight = |first_day: i32, base: f32, per_day: f32| {
+            if day < first_day {
+                0.0
+            } else {
+                base + per_day * (day - first_day) as f32
+            }
+        };
+        match self {
+            EnemyKind::Grunt => weight(1, 10.0, 0.0),
+            EnemyKind::Swarmer => weight(2, 2.0, 0.5),
+            EnemyKind::Charger => weight(3, 2.0, 0.5),
+            EnemyKind::Spitter => weight(4, 1.5, 0.5),
+            EnemyKind::Tank => weight(5, 1.0, 0.3),
+        }
+    }
+
+    /// Columns and rows of the sprite sheet, the walk cycle plays every frame in order
+    pub fn sheet_grid(self) -> (usize, usize) {
+        match self {
+            EnemyKind::Grunt | EnemyKind::Charger | EnemyKind::Tank => (4, 4),
+            EnemyKind::Spitter | EnemyKind::Swarmer => (4, 2),
+        }
+    }
+
+    pub fn frame_count(self) -> usize {
+        let (columns, rows) = self.sheet_grid();
+        columns * rows
+    }
+
+    /// Sprite sheet of the kind, all frames are 200x200 so [`EnemyKind::scale`] sets the size
+    pub fn atlas(self, textures: &TextureAssets) -> TextureAtlas {
+        let texture = match self {
+            EnemyKind::Grunt => &textures.texture_enemy,
+            EnemyKind::Charger => &textures.texture_enemy_charger,
+            EnemyKind::Spitter => &textures.texture_enemy_spitter,
+            EnemyKind::Swarmer => &textures.texture_enemy_swarmer,
+            EnemyKind::Tank => &textures.texture_enemy_tank,
+        };
+        let (columns, rows) = self.sheet_grid();
+        TextureAtlas::from_grid(texture.clone(), Vec2::new(200.0, 200.0), columns, rows, None, None)
+    }
+
+    /// Applied to the player while touching them
+    pub fn contact_effect(self) -> Option<StatusEffect> {
+        match self {
+            EnemyKind::Swarmer => Some(StatusEffect {
+                kind: StatusKind::Poison,
+                duration: 3.0,
+                damage_per_second: 0.5,
+                speed_multiplier: 1.0,
+                stacking: Stacking::Refresh,
+            }),
+            EnemyKind::Tank => Some(StatusEffect {
+                kind: StatusKind::Slow,
+                duration: 1.0,
+                damage_per_second: 0.0,
+                speed_multiplier: 0.6,
+                stacking: Stacking::Refresh,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Walks straight at the player at night
+#[derive(Component)]
+pub struct Chaser;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ChargePhase {
+    Approach,
+    WindUp,
+    Dash,
+    Recover,
+}
+
+#[derive(Component)]
+pub struct Charger {
+    phase: ChargePhase,
+    timer: Timer,
+    direction: Vec3,
+}
+
+impl Default for Charger {
+    fn default() -> Self {
+        Self {
+            phase: ChargePhase::Approach,
+            timer: Timer::from_seconds(0.0, TimerMode::Once),
+            direction: Vec3::ZERO,
+        }
+    }
+}
+
+impl Charger {
+    fn enter(&mut self, phase: ChargePhase, seconds: f32) {
+        self.phase = phase;
+        self.timer = Timer::from_seconds(seconds, TimerMode::Once);
+    }
+}
+
+#[derive(Component)]
+pub struct Spitter {
+    weapon: Weapon,
+}
+
+impl Default for Spitter {
+    fn default() -> Self {
+        Self {
+            weapon: Weapon {
+                cd: 2.0,
+                from_shot: 0.0,
+                pattern: FirePattern::default(),
+                name: "Spit".into(),
+                damage: 5.0,
+                speed: 220.0,
+                lifetime: 3.0,
+                scale: 0.3,
+                modifiers: ProjectileModifiers {
+                    status: Some(StatusEffect {
+                        kind: StatusKind::Slow,
+                        duration: 2.0,
+                        damage_per_second: 0.0,
+                        speed_multiplier: 0.5,
+                        stacking: Stacking::Refresh,
+                    }),
+                    ..default()
+                },
+                energy_cost: 0,
+            },
+        }
+    }
+}
+
+/// Adds the behaviour components of `kind` to a freshly spawned enemy
+pub fn insert_behaviour(entity: &mut EntityCommands, kind: EnemyKind) {
+    match kind {
+        EnemyKind::Charger => {
+            entity.insert(Charger::default());
+        }
+        EnemyKind::Spitter => {
+            entity.insert(Spitter::default());
+        }
+        EnemyKind::Grunt | EnemyKind::Swarmer | EnemyKind::Tank => {
+            entity.insert(Chaser);
+        }
+    }
+}
+
+fn flat_direction(from: Vec3, to: Vec3) -> Vec3 {
+    let mut direction = to - from;
+    direction.z = 0.0;
+    direction.normalize_or_zero()
+}
+
+pub fn move_chargers(
+    day_night: Res<DayNight>,
+    time: Res<SimulationTime>,
+    mut query: Query<(&mut Transform, &Enemy, &mut Charger, Option<&StatusEffects>), Without<Player>>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if !day_night.is_night {
+        return;
+    }
+    let player = player_query.single();
+    for (mut transform, enemy, mut charger, effects) in query.iter_mut() {
+        if effects.is_some_and(StatusEffects::frozen) {
+            continue;
+        }
+        let speed = speed_multiplier(effects);
+        charger.timer.tick(time.delta());
+        let to_player = flat_direction(transform.translation, player.translation);
+        match charger.phase {
+            ChargePhase::Approach => {
+                transform.translation += to_player * enemy.speed * speed * time.delta_seconds();
+                if transform.translation.truncate().distance(player.translation.truncate()) <= CHARGE_RANGE {
+                    charger.enter(ChargePhase::WindUp, CHARGE_WIND_UP);
+                }
+            }
+            ChargePhase::WindUp => {
+                // The direction locks at the end of the wind up, so a quick sidestep dodges the dash
+                charger.direction = to_player;
+                if charger.timer.finished() {
+                    charger.enter(ChargePhase::Dash, CHARGE_DASH);
+                }
+            }
+            ChargePhase::Dash => {
+                transform.translation += charger.direction * CHARGE_SPEED * speed * time.delta_seconds();
+                if charger.timer.finished() {
+                    charger.enter(ChargePhase::Recover, CHARGE_RECOVER);
+                }
+            }
+            ChargePhase::Recover => {
+                if charger.timer.finished() {
+                    charger.enter(ChargePhase::Approach, 0.0);
+                }
+            }
+        }
+    }
+}
+
+pub fn move_spitters(
+    mut commands: Commands,
+    day_night: Res<DayNight>,
+    time: Res<SimulationTime>,
+    textures: Res<TextureAssets>,
+    mut pool: ResMut<BulletPool>,
+    mut query: Query<(Entity, &mut Transform, &Enemy, &mut Spitter, Option<&StatusEffects>), Without<Player>>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if !day_night.is_night {
+        return;
+    }
+    let player = player_query.single();
+    for (entity, mut transform, enemy, mut spitter, effects) in query.iter_mut() {
+        if effects.is_some_and(StatusEffects::frozen) {
+            continue;
+        }
+        let speed = speed_multiplier(effects);
+        let to_player = flat_direction(transform.translation, player.translation);
+        let distance = transform.translation.truncate().distance(player.translation.truncate());
+        if distance > SPIT_DISTANCE + SPIT_DISTANCE_TOLERANCE {
+            transform.translation += to_player * enemy.speed * speed * time.delta_seconds();
+        } else if distance < SPIT_DISTANCE - SPIT_DISTANCE_TOLERANCE {
+            transform.translation -= to_player * enemy.speed * speed * time.delta_seconds();
+        }
+
+        spitter.weapon.from_shot += time.delta_seconds();
+        if distance <= SPIT_RANGE && spitter.weapon.from_shot > spitter.weapon.cd && to_player != Vec3::ZERO {
+            spitter.weapon.from_shot = 0.0;
+            let aim = Transform::from_translation(transform.translation)
+                .with_rotation(Quat::from_rotation_arc(Vec3::Y, to_player));
+            fire_weapon(&mut commands, &textures, &mut pool, entity, Faction::Enemies, &aim, &spitter.weapon);
+        }
+    }
+}
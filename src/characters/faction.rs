@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+/// Side an entity fights on. Projectiles carry the faction of whoever fired them
+/// and hurt everything of the other faction that has `Hp`.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Faction {
+    /// The player and their turrets
+    Player,
+    Enemies,
+}
+
+/// Projectiles also hurt their own faction, except whoever fired them.
+/// Off unless the game is started with `--friendly-fire`.
+#[derive(Resource, Default)]
+pub struct FriendlyFire(pub bool);
+
+impl Faction {
+    /// Whether a projectile of this faction fired by `owner` hurts `target` of faction `target_faction`
+    pub fn hurts(self, owner: Entity, target: Entity, target_faction: Faction, friendly_fire: &FriendlyFire) -> bool {
+        if target == owner {
+            return false;
+        }
+        self != target_faction || friendly_fire.0
+    }
+}
@@ -0,0 +1,132 @@
+use std::f32::consts::TAU;
+
+use bevy::math::vec3;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::loading::TextureAssets;
+use crate::simulation::SimulationTime;
+
+use super::bullets::{shot_bullet, BulletPool};
+use super::faction::Faction;
+use super::player::Weapon;
+
+/// How far in front of the shooter projectiles appear
+const MUZZLE_DISTANCE: f32 = 25.0;
+
+/// How a weapon shoots, e.g. `(count: 3, spread: 30.0)` for a fan of three
+/// or `(count: 24, radial: true)` for a nova. Missing fields take their default.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct FirePattern {
+    /// Projectiles per shot
+    pub count: u32,
+    /// Arc, in degrees, the projectiles fan out over
+    pub spread: f32,
+    /// Sideways distance between projectiles, for parallel rays
+    pub parallel_offset: f32,
+    /// Projectiles go all around, evenly spaced, instead of towards the aim
+    pub radial: bool,
+    /// Rotation of the whole pattern from the aim, in degrees
+    pub angle_offset: f32,
+    /// Shots fired per trigger pull
+    pub burst: u32,
+    /// Seconds between the shots of a burst
+    pub burst_delay: f32,
+}
+
+impl Default for FirePattern {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            spread: 0.0,
+            parallel_offset: 0.0,
+            radial: false,
+            angle_offset: 0.0,
+            burst: 1,
+            burst_delay: 0.1,
+        }
+    }
+}
+
+/// The rest of a burst, fired from wherever the shooter is and aims at the time
+#[derive(Component)]
+pub struct Burst {
+    weapon: Weapon,
+    faction: Faction,
+    remaining: u32,
+    timer: Timer,
+}
+
+/// Pulls the trigger of `weapon` held by `shooter`. The first shot goes out immediately,
+/// the rest of a burst follows through [`fire_bursts`]. Cooldown and energy are up to the caller.
+/// The projectiles belong to `faction` and never hurt `shooter`.
+pub fn fire_weapon(
+    commands: &mut Commands,
+    textures: &Res<TextureAssets>,
+    pool: &mut BulletPool,
+    shooter: Entity,
+    faction: Faction,
+    transform: &Transform,
+    weapon: &Weapon,
+) {
+    emit(commands, textures, pool, shooter, faction, transform, weapon);
+    if weapon.pattern.burst > 1 {
+        commands.entity(shooter).insert(Burst {
+            weapon: weapon.clone(),
+            faction,
+            remaining: weapon.pattern.burst - 1,
+            timer: Timer::from_seconds(weapon.pattern.burst_delay.max(0.0), TimerMode::Repeating),
+        });
+    }
+}
+
+/// One shot of the pattern, aimed along the local Y axis of `transform`
+fn emit(
+    commands: &mut Commands,
+    textures: &Res<TextureAssets>,
+    pool: &mut BulletPool,
+    shooter: Entity,
+    faction: Faction,
+    transform: &Transform,
+    weapon: &Weapon,
+) {
+    let pattern = &weapon.pattern;
+    let forward = transform.rotation * Vec3::Y;
+    let aim = Quat::from_rotation_z(pattern.angle_offset.to_radians()) * vec3(forward.x, forward.y, 0.0).normalize_or_zero();
+    let count = pattern.count.max(1);
+    let middle = (count - 1) as f32 / 2.0;
+
+    for i in 0..count {
+        let (direction, offset) = if pattern.radial {
+            (Quat::from_rotation_z(TAU * i as f32 / count as f32) * aim, 0.0)
+        } else {
+            let fraction = if count > 1 { i as f32 / (count - 1) as f32 - 0.5 } else { 0.0 };
+            let direction = Quat::from_rotation_z((fraction * pattern.spread).to_radians()) * aim;
+            (direction, (i as f32 - middle) * pattern.parallel_offset)
+        };
+        let side = vec3(aim.y, -aim.x, 0.0);
+        let position = transform.translation + direction * MUZZLE_DISTANCE + side * offset;
+        shot_bullet(commands, textures, pool, shooter, faction, position, direction, weapon);
+    }
+}
+
+pub fn fire_bursts(
+    mut commands: Commands,
+    time: Res<SimulationTime>,
+    textures: Res<TextureAssets>,
+    mut pool: ResMut<BulletPool>,
+    mut query: Query<(Entity, &Transform, &mut Burst)>,
+) {
+    for (entity, transform, mut burst) in query.iter_mut() {
+        burst.timer.tick(time.delta());
+        let shots = burst.timer.times_finished_this_tick().min(burst.remaining);
+        for _ in 0..shots {
+            emit(&mut commands, &textures, &mut pool, entity, burst.faction, transform, &burst.weapon);
+        }
+        burst.remaining -= shots;
+        if burst.remaining == 0 {
+            commands.entity(entity).remove::<Burst>();
+        }
+    }
+}
@@ -1,6 +1,13 @@
+pub mod arsenal;
 pub mod base_character;
+pub mod damage;
 pub mod player;
 pub mod enemy;
+pub mod enemy_kinds;
+pub mod faction;
 pub mod turret;
 pub mod bullets;
-pub mod cleaner;
\ No newline at end of file
+pub mod cleaner;
+pub mod fire_pattern;
+pub mod projectile_modifiers;
+pub mod status_effects;
\ No newline at end of file
@@ -1,43 +1,75 @@
-use std::f32::consts::PI;
-
 use crate::GameState;
 use crate::actions::Actions;
-use crate::interactive_items::chest::WEAPONS;
 use crate::loading::TextureAssets;
+use crate::collision::Collider;
+use crate::game_events::{ShotFired, TurretPlaced, WeaponSlot, WeaponSwapped};
 use crate::map::DayNight;
-use crate::ui::UiLog;
+use crate::simulation::{interpolate_transforms, Interpolated, SimulationSet, SimulationTime};
 
-use bevy::math::vec3;
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
 use bevy::window::{WindowResized, PrimaryWindow};
 use bevy_easings::Lerp;
 use crate::characters::base_character::*;
 
-use super::bullets::shot_bullet;
-use super::turret::Turret;
+use super::arsenal::Arsenal;
+use super::bullets::BulletPool;
+use super::damage::{Invulnerable, Knockback, MaxHp, Shield};
+use super::projectile_modifiers::ProjectileModifiers;
+use super::status_effects::{speed_multiplier, StatusEffects};
+use super::fire_pattern::{fire_weapon, FirePattern};
+use super::faction::Faction;
+use super::turret::spawn_turret;
 pub struct PlayerPlugin;
 
 #[derive(Component)]
 pub struct Player;
 
-#[derive(Clone, Copy)]
-pub enum WeaponPattern {
-    Single,
-    TwoShot,
-    SixRay,
-    SixAround,
-    ManyAround
-}
 #[derive(Component, Clone)]
 pub struct Weapon {
     pub cd: f32,
     pub from_shot: f32,
-    pub pattern: WeaponPattern,
-    pub name: String
+    pub pattern: FirePattern,
+    pub name: String,
+    /// Damage of one projectile
+    pub damage: f32,
+    /// Projectile speed, in pixels per second
+    pub speed: f32,
+    /// Seconds a projectile flies before it disappears
+    pub lifetime: f32,
+    /// Size of the projectile sprite, its hit radius grows with it
+    pub scale: f32,
+    pub modifiers: ProjectileModifiers,
+    /// Energy used per shot by the player, turrets fire for free
+    pub energy_cost: i32,
+}
+
+impl Weapon {
+    /// Short summary for the HUD and pickup prompts
+    pub fn stats(&self) -> String {
+        let projectiles = self.pattern.count.max(1) * self.pattern.burst.max(1);
+        format!(
+            "{}x{} dmg{}, speed {}, range {}, {} energy",
+            projectiles,
+            self.damage,
+            self.modifiers.describe(),
+            self.speed,
+            (self.speed * self.lifetime).round(),
+            self.energy_cost
+        )
+    }
 }
 #[derive(Component)]
 pub struct Energy(pub i32);
 
+pub const PLAYER_HP: f32 = 100.0;
+
+/// Seconds the player can't be hurt again after a hit
+pub const PLAYER_INVULNERABILITY: f32 = 0.5;
+
+/// How long, in seconds, an item found in a chest can be taken
+pub const OFFER_TIME: f64 = 5.0;
+
 #[derive(Resource, Default)]
 pub struct TemporaryItems {
     pub weapon: Option<Weapon>,
@@ -45,16 +77,18 @@ pub struct TemporaryItems {
     pub timestamp: f64
 }
 
-#[derive(Resource)]
+impl TemporaryItems {
+    pub fn offer_active(&self, now: f64) -> bool {
+        (self.weapon.is_some() || self.turret.is_some()) && now - self.timestamp <= OFFER_TIME
+    }
+}
+
+/// Filled with the starting turret of the [`Arsenal`] when the player spawns
+#[derive(Resource, Default)]
 pub struct Inventoty {
     pub turret: Option<Weapon>
 }
 
-impl Default for Inventoty {
-    fn default() -> Self {        
-        Self { turret: Some(WEAPONS[0].clone()) }
-    }
-}
 /// This plugin handles player related stuff like movement
 /// Player logic is only active during the State `GameState::Playing`
 impl Plugin for PlayerPlugin {
@@ -63,7 +97,15 @@ impl Plugin for PlayerPlugin {
             .init_resource::<TemporaryItems>()
             .init_resource::<Inventoty>()
             .add_systems(OnEnter(GameState::Playing), spawn_player)
-            .add_systems(Update, (check_resolution, move_player, move_camera, animate_sprite, fire, change_weapon, place_turret, check_win, check_death)
+            .add_systems(FixedUpdate, (move_player, fire, change_weapon, place_turret, check_win, check_death)
+                .chain()
+                .in_set(SimulationSet::Player)
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(Update, animate_sprite.run_if(in_state(GameState::Playing)))
+            // Follows the drawn position of the player, after interpolation
+            .add_systems(PostUpdate, move_camera
+                .after(interpolate_transforms)
+                .before(TransformSystem::TransformPropagate)
                 .run_if(in_state(GameState::Playing)));
     }
 }
@@ -73,7 +115,7 @@ fn set_aspect_for_camera(projection: &mut OrthographicProjection, w: f32){
     projection.scale = 800.0 / w;
 }
 
-fn check_resolution(mut query: Query<&mut OrthographicProjection, With<Camera>>, resize_event: Res<Events<WindowResized>>){
+pub fn check_resolution(mut query: Query<&mut OrthographicProjection, With<Camera>>, resize_event: Res<Events<WindowResized>>){
     let mut reader = resize_event.get_reader();
     for e in reader.iter(&resize_event) {
         println!("width = {} height = {}", e.width, e.height);
@@ -99,8 +141,10 @@ fn check_win(
     }
 }
 
-fn spawn_player(mut commands: Commands, 
+pub fn spawn_player(mut commands: Commands, 
     textures: Res<TextureAssets>,
+    arsenal: Res<Arsenal>,
+    mut inventory: ResMut<Inventoty>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     primary_window: Query<&Window, With<PrimaryWindow>>
 ) {
@@ -127,10 +171,11 @@ fn spawn_player(mut commands: Commands,
         },
         ..Default::default()
     });
+    let translation = Vec3 { x: 0., y: 25.0, z: 0.1 };
     commands.spawn( SpriteBundle {
         texture: textures.texture_shadow.clone(),
         transform: Transform {
-            translation: Vec3 { x: 0., y: 25.0, z: 0.1 },
+            translation,
             scale: Vec3::splat(0.25),
             ..Default::default()
         },
@@ -159,14 +204,18 @@ fn spawn_player(mut commands: Commands,
     }) 
     
     .insert(Player)
-    .insert(Hp(100.0))
+    .insert(Interpolated::new(translation))
+    .insert(Hp(PLAYER_HP))
+    .insert(MaxHp(PLAYER_HP))
+    .insert(Shield::new(25.0, 5.0, 3.0))
+    .insert(Invulnerable::after_hit(PLAYER_INVULNERABILITY))
+    .insert(Knockback::default())
+    .insert(Faction::Player)
+    .insert(Collider::circle(80.0))
+    .insert(StatusEffects::default())
     .insert(Energy(100))
-    .insert(Weapon {
-        cd: 0.2,
-        from_shot: 0.0,
-        pattern: WeaponPattern::Single,
-        name: "Base Eradicator".into()
-    });
+    .insert(arsenal.starting_weapon.clone());
+    inventory.turret = Some(arsenal.starting_turret.clone());
 
 }
 
@@ -184,16 +233,17 @@ fn move_camera(
 }
 
 fn move_player(
-    time: Res<Time>,
+    time: Res<SimulationTime>,
     actions: Res<Actions>,
-    mut player_query: Query<(&mut Transform, &mut Weapon, &Children), With<Player>>,
+    mut player_query: Query<(&mut Transform, &mut Weapon, &Children, Option<&StatusEffects>), With<Player>>,
     mut sprites: Query<&mut AnimationIndices>
 ) {
     let movement;
     let mut anim_indices = (4,4);
-    if actions.player_movement.is_some() {
+    let slowed = player_query.get_single().map_or(1.0, |(_, _, _, effects)| speed_multiplier(effects));
+    if actions.player_movement.is_some() && slowed > 0.0 {
 
-        let speed = 150.;
+        let speed = 150. * slowed;
         movement = Vec3::new(
             actions.player_movement.unwrap().x * speed * time.delta_seconds(),
             actions.player_movement.unwrap().y * speed * time.delta_seconds(),
@@ -203,7 +253,7 @@ fn move_player(
     } else {
         movement = Vec3::ZERO;
     }
-    for (mut player_transform, mut weapon, children) in &mut player_query {
+    for (mut player_transform, mut weapon, children, _) in &mut player_query {
         weapon.from_shot += time.delta_seconds();
         player_transform.translation += movement;
         player_transform.rotation =  Quat::from_rotation_z(actions.mouse_angle);
@@ -219,79 +269,28 @@ fn move_player(
 
 fn fire(
     mut commands: Commands,
-    mut query: Query<(&Transform, &mut Weapon, &mut Energy), With<Player>>, 
-    buttons: Res<Input<MouseButton>>,
+    mut query: Query<(Entity, &Transform, &mut Weapon, &mut Energy, Option<&StatusEffects>), With<Player>>, 
+    actions: Res<Actions>,
     textures: Res<TextureAssets>,
+    mut pool: ResMut<BulletPool>,
+    mut shots: EventWriter<ShotFired>,
 ){
-    if buttons.pressed(MouseButton::Left) {
-        let (pl_transform, mut weapon, mut energy) = query.single_mut();
+    if actions.fire.pressed {
+        let (entity, pl_transform, mut weapon, mut energy, effects) = query.single_mut();
+        if effects.is_some_and(StatusEffects::frozen) {
+            return;
+        }
         if weapon.from_shot > weapon.cd && energy.0 > 0 {
             weapon.from_shot = 0.0;
-            let velocity = (pl_transform.rotation * Vec3::Y).normalize_or_zero();
-            match weapon.pattern {
-                WeaponPattern::Single => {
-                    shot_bullet(&mut commands, &textures, pl_transform.translation + velocity*25.0, velocity, 1.0, 300.0);
-                    energy.0 -= 1;
-                },
-                WeaponPattern::TwoShot => {
-                    let velocity = Quat::from_axis_angle(Vec3::Z, -PI/16.0) * velocity;
-                    shot_bullet(&mut commands, &textures, pl_transform.translation + velocity*25.0, velocity, 1.0, 300.0);
-                    let velocity = Quat::from_axis_angle(Vec3::Z, PI/16.0) * velocity;
-                    shot_bullet(&mut commands, &textures, pl_transform.translation + velocity*25.0, velocity, 1.0, 300.0);
-                    energy.0 -= 2;
-                },
-                WeaponPattern::SixRay => {
-                    let points = vec![
-                        pl_transform.rotation*vec3(-15.0, 25.0, pl_transform.translation.z),
-                        pl_transform.rotation*vec3(-10.0, 25.0, pl_transform.translation.z),
-                        pl_transform.rotation*vec3(-5.0, 25.0, pl_transform.translation.z),
-                        pl_transform.rotation*vec3(5.0, 25.0, pl_transform.translation.z),
-                        pl_transform.rotation*vec3(10.0, 25.0, pl_transform.translation.z),
-                        pl_transform.rotation*vec3(15.0, 25.0, pl_transform.translation.z),
-                    ];
-                    for p in points {
-                        shot_bullet(&mut commands, &textures, pl_transform.translation + p, velocity, 1.0, 300.0);
-                    }
-     
-                    energy.0 -= 6;
-                },
-                WeaponPattern::SixAround => {
-                    let points = vec![
-                        Quat::from_axis_angle(Vec3::Z, 0.0) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/3.0) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/1.5) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI + PI/1.5) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI + PI/3.0) * velocity * 25.0,
-                    ];
-                    for p in points {
-                        shot_bullet(&mut commands, &textures, pl_transform.translation + p, p.normalize(), 1.0, 300.0);
-                    }
-                    energy.0 -= 6;
-                },
-                WeaponPattern::ManyAround => {
-                    let points = vec![
-                        Quat::from_axis_angle(Vec3::Z, 0.0) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/3.0) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/1.5) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI + PI/1.5) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI + PI/3.0) * velocity * 25.0,
-
-                        Quat::from_axis_angle(Vec3::Z, PI/6.0) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/6.0+PI/3.0) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/6.0+PI/1.5) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/6.0+PI) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/6.0+PI + PI/1.5) * velocity * 25.0,
-                        Quat::from_axis_angle(Vec3::Z, PI/6.0+PI + PI/3.0) * velocity * 25.0,
-                    ];
-                    for p in points {
-                        shot_bullet(&mut commands, &textures, pl_transform.translation + p, p.normalize(), 1.0, 300.0);
-                    }
-                    energy.0 -= 12;
-                },
-            }
+            fire_weapon(&mut commands, &textures, &mut pool, entity, Faction::Player, pl_transform, &weapon);
+            let before = energy.0;
+            energy.0 -= weapon.energy_cost;
             energy.0 = energy.0.clamp(0, 500);
+            shots.send(ShotFired {
+                weapon: weapon.name.clone(),
+                projectiles: weapon.pattern.count.max(1) * weapon.pattern.burst.max(1),
+                energy: before - energy.0,
+            });
         }
     }
 }
@@ -300,34 +299,26 @@ fn fire(
 fn change_weapon(
     mut inventory: ResMut<Inventoty>,
     mut temporary: ResMut<TemporaryItems>,
-    time: Res<Time>,
+    time: Res<SimulationTime>,
     mut query: Query<&mut Weapon, With<Player>>,
-    keys: Res<Input<KeyCode>>,
-    mut log: ResMut<UiLog>
+    actions: Res<Actions>,
+    mut swapped: EventWriter<WeaponSwapped>,
 ) {
-    if keys.just_pressed(KeyCode::R){
-        if temporary.weapon.is_some() && time.elapsed_seconds_f64() - temporary.timestamp <= 5.0 {
+    if actions.accept_offer.just_pressed {
+        if temporary.weapon.is_some() && time.elapsed_seconds_f64() - temporary.timestamp <= OFFER_TIME {
             let mut weapon = query.single_mut();
             if let Some(random_weapon) = &temporary.weapon {
-                weapon.name = random_weapon.name.clone();
-                weapon.cd = random_weapon.cd;
-                weapon.pattern = random_weapon.pattern;
-                log.message_time_stamp = time.elapsed_seconds_f64();
-                log.last_message = format!("Weapon changed to {}", weapon.name);
+                let old = weapon.name.clone();
+                *weapon = Weapon { from_shot: weapon.from_shot, ..random_weapon.clone() };
+                swapped.send(WeaponSwapped { slot: WeaponSlot::Hand, old: Some(old), new: weapon.name.clone() });
                 temporary.weapon = None;
             }
         }
-        else if temporary.turret.is_some()  && time.elapsed_seconds_f64() - temporary.timestamp <= 5.0 {
+        else if temporary.turret.is_some()  && time.elapsed_seconds_f64() - temporary.timestamp <= OFFER_TIME {
             if let Some(random_weapon) = &temporary.turret {
-                let new_turret = Weapon {
-                    cd: random_weapon.cd,
-                    from_shot: 0.0,
-                    pattern: random_weapon.pattern,
-                    name: random_weapon.name.clone(),
-                };
-                inventory.turret = Some(new_turret);
-                log.message_time_stamp = time.elapsed_seconds_f64();
-                log.last_message = format!("Turret changed to {}", random_weapon.name);
+                let new_turret = Weapon { from_shot: 0.0, ..random_weapon.clone() };
+                let old = inventory.turret.replace(new_turret).map(|turret| turret.name);
+                swapped.send(WeaponSwapped { slot: WeaponSlot::Turret, old, new: random_weapon.name.clone() });
                 temporary.turret = None;
             }
         }
@@ -338,20 +329,21 @@ fn change_weapon(
 fn place_turret(
     mut inventory: ResMut<Inventoty>,
     mut commands: Commands,
-    query: Query<&Transform, With<Player>>,
-    keys: Res<Input<KeyCode>>,
+    query: Query<(&Transform, Option<&StatusEffects>), With<Player>>,
+    actions: Res<Actions>,
     textures: Res<TextureAssets>,
+    mut placed: EventWriter<TurretPlaced>,
 ) {
-    if keys.just_pressed(KeyCode::E) {
+    if actions.place_turret.just_pressed {
+        let (transform, effects) = query.single();
+        // Frozen players keep their turret for later
+        if effects.is_some_and(StatusEffects::frozen) {
+            return;
+        }
         if let Some(turret) = &inventory.turret {
-            let transform = query.single().translation;
-            commands.spawn(
-                SpriteBundle {
-                    texture: textures.texture_turret.clone(),
-                    transform: Transform::from_translation(transform).with_scale(Vec3::splat(0.25)),
-                    ..Default::default()
-                }
-            ).insert(Turret::default()).insert(turret.clone());
+            let transform = transform.translation;
+            let entity = spawn_turret(&mut commands, &textures, turret, transform);
+            placed.send(TurretPlaced { turret: entity, weapon: turret.name.clone(), position: transform.truncate() });
         }
         inventory.turret = None;
     }
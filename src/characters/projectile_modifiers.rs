@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::status_effects::StatusEffect;
+
+/// Extra behaviours of the projectiles of a weapon, e.g. `(pierce: 2, homing: 90.0)`.
+/// They combine: a piercing bullet goes through its targets first, then ricochets,
+/// and an explosive one explodes at every hit. Missing fields take their default, which is none of them.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ProjectileModifiers {
+    /// Targets a projectile goes through before it stops
+    pub pierce: u32,
+    /// Times a projectile bounces to the closest other target instead of stopping
+    pub ricochet: u32,
+    /// Area damage around every hit
+    pub explosion: Option<Explosion>,
+    /// How fast a projectile turns towards the closest target, in degrees per second
+    pub homing: f32,
+    /// Applied to the target hit
+    pub status: Option<StatusEffect>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Explosion {
+    /// In pixels
+    pub radius: f32,
+    /// Dealt to everything else in the radius, the target hit takes the projectile damage
+    pub damage: f32,
+}
+
+impl ProjectileModifiers {
+    /// Short summary for [`Weapon::stats`](super::player::Weapon::stats), empty without modifiers
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.pierce > 0 {
+            parts.push(format!("pierces {}", self.pierce));
+        }
+        if self.ricochet > 0 {
+            parts.push(format!("ricochets {}", self.ricochet));
+        }
+        if let Some(explosion) = self.explosion {
+            parts.push(format!("explodes for {}", explosion.damage));
+        }
+        if self.homing > 0.0 {
+            parts.push("homing".to_string());
+        }
+        if let Some(status) = self.status {
+            parts.push(status.describe());
+        }
+        parts.iter().map(|part| format!(", {}", part)).collect()
+    }
+}
+
+/// `direction` turned towards `wanted` by at most `max_angle` radians, both are unit vectors
+pub fn steer(direction: Vec2, wanted: Vec2, max_angle: f32) -> Vec2 {
+    let angle = direction.angle_between(wanted);
+    if angle.is_nan() {
+        return direction;
+    }
+    Vec2::from_angle(angle.clamp(-max_angle, max_angle)).rotate(direction)
+}
@@ -0,0 +1,270 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::{SimulationSet, SimulationTime};
+use crate::GameState;
+
+use super::damage::DamageEvent;
+use super::enemy::Enemy;
+
+pub struct StatusEffectsPlugin;
+
+/// This plugin applies [`StatusEvent`]s and ticks the [`StatusEffects`] of everything that has them
+impl Plugin for StatusEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StatusEvent>()
+            .add_systems(FixedUpdate, (apply_status_events, tick_status_effects)
+                .chain()
+                .in_set(SimulationSet::Effects)
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(Update, tint_affected_enemies.run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusKind {
+    Burn,
+    Poison,
+    Slow,
+    /// Can't move or attack
+    Freeze,
+}
+
+impl StatusKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusKind::Burn => "Burning",
+            StatusKind::Poison => "Poisoned",
+            StatusKind::Slow => "Slowed",
+            StatusKind::Freeze => "Frozen",
+        }
+    }
+
+    /// Tint of affected enemies and of the HUD icon
+    pub fn color(self) -> Color {
+        match self {
+            StatusKind::Burn => Color::rgb(1.0, 0.45, 0.15),
+            StatusKind::Poison => Color::rgb(0.45, 0.9, 0.25),
+            StatusKind::Slow => Color::rgb(0.55, 0.55, 1.0),
+            StatusKind::Freeze => Color::rgb(0.6, 0.95, 1.0),
+        }
+    }
+}
+
+/// What happens when an effect is applied to a target that already has it
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Stacking {
+    /// The timer starts over
+    #[default]
+    Refresh,
+    /// The duration is added to the time left
+    Extend,
+    /// One more stack, up to `max`, and the timer starts over. Damage and slow grow with the stacks.
+    Stack { max: u32 },
+    /// Nothing, the effect runs out first
+    Ignore,
+}
+
+/// A timed effect, e.g. `(kind: Burn, duration: 3.0, damage_per_second: 1.0, stacking: Stack(max: 3))`.
+/// Fields other than `kind` may be left out.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Seconds
+    #[serde(default)]
+    pub duration: f32,
+    #[serde(default)]
+    pub damage_per_second: f32,
+    /// Movement speed is multiplied by this, ignored by [`StatusKind::Freeze`] which stops the target
+    #[serde(default = "no_slow")]
+    pub speed_multiplier: f32,
+    #[serde(default)]
+    pub stacking: Stacking,
+}
+
+fn no_slow() -> f32 {
+    1.0
+}
+
+impl StatusEffect {
+    pub fn describe(&self) -> String {
+        format!("{} {}s", self.kind.name().to_lowercase(), self.duration)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActiveEffect {
+    pub effect: StatusEffect,
+    /// Seconds left
+    pub remaining: f32,
+    pub stacks: u32,
+    /// Who applied it last and with what, credited for its damage.
+    /// Entities don't outlive the run, so a saved effect only keeps the weapon.
+    #[serde(skip)]
+    pub by: Option<Entity>,
+    pub weapon: Option<String>,
+}
+
+/// Effects on an entity, one entry per kind
+#[derive(Component, Clone, Default, Serialize, Deserialize)]
+pub struct StatusEffects(pub Vec<ActiveEffect>);
+
+impl StatusEffects {
+    fn apply(&mut self, event: &StatusEvent) {
+        let effect = event.effect;
+        let Some(active) = self.0.iter_mut().find(|active| active.effect.kind == effect.kind) else {
+            self.0.push(ActiveEffect {
+                effect,
+                remaining: effect.duration,
+                stacks: 1,
+                by: event.by,
+                weapon: event.weapon.clone(),
+            });
+            return;
+        };
+        match effect.stacking {
+            Stacking::Refresh => {
+                active.effect = effect;
+                active.remaining = effect.duration;
+            }
+            Stacking::Extend => active.remaining += effect.duration,
+            Stacking::Stack { max } => {
+                active.effect = effect;
+                active.remaining = effect.duration;
+                active.stacks = (active.stacks + 1).min(max.max(1));
+            }
+            Stacking::Ignore => return,
+        }
+        active.by = event.by;
+        active.weapon = event.weapon.clone();
+    }
+
+    /// Multiplier for movement speed, every effect counts
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.frozen() {
+            return 0.0;
+        }
+        self.0
+            .iter()
+            .map(|active| active.effect.speed_multiplier.powi(active.stacks as i32))
+            .product()
+    }
+
+    /// Frozen targets can't move or attack
+    pub fn frozen(&self) -> bool {
+        self.0.iter().any(|active| active.effect.kind == StatusKind::Freeze)
+    }
+}
+
+/// Movement speed multiplier of an entity that may have effects
+pub fn speed_multiplier(effects: Option<&StatusEffects>) -> f32 {
+    effects.map_or(1.0, StatusEffects::speed_multiplier)
+}
+
+/// Applies `effect` to `target`, sent by hits and attacks
+#[derive(Event)]
+pub struct StatusEvent {
+    pub target: Entity,
+    pub effect: StatusEffect,
+    /// Who applied it and with what, credited for the damage it deals
+    pub by: Option<Entity>,
+    pub weapon: Option<String>,
+}
+
+fn apply_status_events(mut events: EventReader<StatusEvent>, mut query: Query<&mut StatusEffects>) {
+    for event in events.iter() {
+        if let Ok(mut effects) = query.get_mut(event.target) {
+            effects.apply(event);
+        }
+    }
+}
+
+fn tick_status_effects(
+    time: Res<SimulationTime>,
+    mut damage: EventWriter<DamageEvent>,
+    mut query: Query<(Entity, &mut StatusEffects)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut effects) in query.iter_mut() {
+        if effects.0.is_empty() {
+            continue;
+        }
+        for active in effects.0.iter_mut() {
+            let amount = active.effect.damage_per_second * active.stacks as f32 * delta.min(active.remaining);
+            if amount > 0.0 {
+                damage.send(DamageEvent {
+                    by: active.by,
+                    weapon: active.weapon.clone(),
+                    ..DamageEvent::over_time(entity, amount)
+                });
+            }
+            active.remaining -= delta;
+        }
+        effects.0.retain(|active| active.remaining > 0.0);
+    }
+}
+
+/// Enemies take the color of their most recent effect
+fn tint_affected_enemies(mut query: Query<(&StatusEffects, &mut TextureAtlasSprite), With<Enemy>>) {
+    for (effects, mut sprite) in query.iter_mut() {
+        sprite.color = match effects.0.last() {
+            Some(active) => active.effect.kind.color(),
+            None => Color::WHITE,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn burn(duration: f32, stacking: Stacking) -> StatusEvent {
+        StatusEvent {
+            target: Entity::PLACEHOLDER,
+            effect: StatusEffect {
+                kind: StatusKind::Burn,
+                duration,
+                damage_per_second: 1.0,
+                speed_multiplier: 1.0,
+                stacking,
+            },
+            by: None,
+            weapon: None,
+        }
+    }
+
+    #[test]
+    fn refresh_restarts_the_timer() {
+        let mut effects = StatusEffects::default();
+        effects.apply(&burn(3.0, Stacking::Refresh));
+        effects.0[0].remaining = 2.5;
+        effects.apply(&burn(1.0, Stacking::Refresh));
+        assert_eq!(effects.0.len(), 1);
+        assert_eq!(effects.0[0].remaining, 1.0);
+        assert_eq!(effects.0[0].stacks, 1);
+    }
+
+    #[test]
+    fn extend_adds_the_duration() {
+        let mut effects = StatusEffects::default();
+        effects.apply(&burn(3.0, Stacking::Extend));
+        effects.0[0].remaining = 1.0;
+        effects.apply(&burn(3.0, Stacking::Extend));
+        assert_eq!(effects.0[0].remaining, 4.0);
+        assert_eq!(effects.0[0].stacks, 1);
+    }
+
+    #[test]
+    fn stack_counts_up_to_max() {
+        let mut effects = StatusEffects::default();
+        for _ in 0..5 {
+            effects.apply(&burn(2.0, Stacking::Stack { max: 3 }));
+            effects.0[0].remaining = 0.5;
+        }
+        assert_eq!(effects.0.len(), 1);
+        assert_eq!(effects.0[0].stacks, 3);
+
+        effects.apply(&burn(2.0, Stacking::Stack { max: 3 }));
+        assert_eq!(effects.0[0].remaining, 2.0);
+    }
+}
@@ -1,25 +1,53 @@
 use std::f32::consts::PI;
 
+use crate::collision::Collider;
 use crate::loading::TextureAssets;
 use crate::GameState;
+use crate::game_events::TurretDestroyed;
+use crate::simulation::{SimulationSet, SimulationTime};
+use crate::spatial_grid::SpatialGrid;
 
 
 
-use bevy::math::{vec3, vec2};
+use bevy::math::vec2;
 use bevy::prelude::*;
 
-use super::bullets::shot_bullet;
+use super::base_character::Hp;
+use super::bullets::BulletPool;
+use super::damage::MaxHp;
+use super::faction::Faction;
+use super::fire_pattern::fire_weapon;
 use super::enemy::Enemy;
-use super::player::{Weapon, WeaponPattern};
+use super::player::Weapon;
+use super::status_effects::StatusEffects;
 
 
 pub struct TurretPlugin;
 
+/// Turrets can be shot down by enemy projectiles
+pub const TURRET_HP: f32 = 30.0;
+/// Turrets pick the closest enemy within this many pixels
+const TURRET_RANGE: f32 = 316.0;
+
 #[derive(Component, Default)]
 pub struct Turret{
     target: Option<Entity>
 }
 
+/// A turret firing `weapon`, placed by the player or restored from a save
+pub fn spawn_turret(commands: &mut Commands, textures: &TextureAssets, weapon: &Weapon, position: Vec3) -> Entity {
+    commands.spawn(
+        SpriteBundle {
+            texture: textures.texture_turret.clone(),
+            transform: Transform::from_translation(position).with_scale(Vec3::splat(0.25)),
+            ..Default::default()
+        }
+    ).insert(Turret::default()).insert(weapon.clone())
+    .insert(Hp(TURRET_HP)).insert(MaxHp(TURRET_HP)).insert(Faction::Player).insert(StatusEffects::default())
+    .insert(Collider::aabb(Vec2::splat(140.0)))
+    .id()
+}
+
 /// This plugin handles Turret related stuff like movement
 /// Turret logic is only active during the State `GameState::Playing`
 impl Plugin for TurretPlugin {
@@ -27,23 +55,26 @@ impl Plugin for TurretPlugin {
         app
 
         //.add_systems(OnEnter(GameState::Playing), spawn_enemy)
-        .add_systems(Update, (reload_turrets, check_for_enemy, fire_turret).run_if(in_state(GameState::Playing)))
+        .add_systems(FixedUpdate, (reload_turrets, check_for_enemy, fire_turret, check_death)
+            .chain()
+            .in_set(SimulationSet::Turrets)
+            .run_if(in_state(GameState::Playing)))
         ;
     }
 }
 
 fn check_for_enemy(
-    
+    grid: Res<SpatialGrid>,
     mut query: Query<(&mut Turret, &Transform)>,
     query_enemies: Query<(Entity, &Transform), With<Enemy>>
 ) {
     for (mut turret, transform) in query.iter_mut() {
         if turret.target.is_none() {
-            let mut nearest_dist = 100000.0;
+            let mut nearest_dist = TURRET_RANGE * TURRET_RANGE;
             let mut target : Option<Entity> = None;
-            for (entity, transform_enemy) in query_enemies.iter() {
+            for (entity, transform_enemy) in grid.near(transform.translation.truncate(), TURRET_RANGE).filter_map(|e| query_enemies.get(e).ok()) {
                 let dist = transform.translation.distance_squared(transform_enemy.translation);
-                if  dist < 1000000.0 && dist < nearest_dist {
+                if dist < nearest_dist {
                     target = Some(entity);
                     nearest_dist = dist;
                 }
@@ -62,7 +93,7 @@ fn check_for_enemy(
 }
 
 fn reload_turrets(
-    time: Res<Time>,
+    time: Res<SimulationTime>,
     mut query: Query<&mut Weapon, With<Turret>>,
 ){
     for mut turret_weapon in query.iter_mut() {
@@ -72,12 +103,13 @@ fn reload_turrets(
 
 fn fire_turret(
     mut commands: Commands,
-    mut query: Query<(&mut Transform, &mut Weapon, &Turret), (With<Turret>, Without<Enemy>)>,
+    mut query: Query<(Entity, &mut Transform, &mut Weapon, &Turret), (With<Turret>, Without<Enemy>)>,
     query_enemies: Query<&Transform, With<Enemy>>,
     textures: Res<TextureAssets>,
+    mut pool: ResMut<BulletPool>,
 ){
 
-    for  (mut pl_transform, mut weapon, turret) in query.iter_mut(){
+    for  (entity, mut pl_transform, mut weapon, turret) in query.iter_mut(){
         if let Some(target) = turret.target {
             if let Ok(enemy) = query_enemies.get(target){
                 let mut temp_enemy_trans  = enemy.translation;
@@ -86,72 +118,27 @@ fn fire_turret(
                 let dir = (temp_enemy_trans - pl_transform.translation).normalize_or_zero();
                 let dir2 = vec2(dir.x, dir.y).normalize_or_zero();
                 let angle = PI - dir2.angle_between(Vec2::NEG_Y);
-                info!("Dir: {} Angle {}", dir2, angle.to_degrees());
                 pl_transform.rotation = Quat::from_rotation_z(angle);
                 if weapon.from_shot > weapon.cd*2.0 {
                     weapon.from_shot = 0.0;
-                    // let velocity = (pl_transform.rotation * Vec3::Y).normalize_or_zero();
-                    let velocity = dir;
-                    match weapon.pattern {
-                        WeaponPattern::Single => {
-                            shot_bullet(&mut commands, &textures, pl_transform.translation + velocity*25.0, velocity, 1.0, 300.0);
-                        },
-                        WeaponPattern::TwoShot => {
-                            let velocity = Quat::from_axis_angle(Vec3::Z, -PI/16.0) * velocity;
-                            shot_bullet(&mut commands, &textures, pl_transform.translation + velocity*25.0, velocity, 1.0, 300.0);
-                            let velocity = Quat::from_axis_angle(Vec3::Z, PI/16.0) * velocity;
-                            shot_bullet(&mut commands, &textures, pl_transform.translation + velocity*25.0, velocity, 1.0, 300.0);
-                        },
-                        WeaponPattern::SixRay => {
-                            let points = vec![
-                                pl_transform.rotation*vec3(-15.0, 25.0, pl_transform.translation.z),
-                                pl_transform.rotation*vec3(-10.0, 25.0, pl_transform.translation.z),
-                                pl_transform.rotation*vec3(-5.0, 25.0, pl_transform.translation.z),
-                                pl_transform.rotation*vec3(5.0, 25.0, pl_transform.translation.z),
-                                pl_transform.rotation*vec3(10.0, 25.0, pl_transform.translation.z),
-                                pl_transform.rotation*vec3(15.0, 25.0, pl_transform.translation.z),
-                            ];
-                            for p in points {
-                                shot_bullet(&mut commands, &textures, pl_transform.translation + p, velocity, 1.0, 300.0);
-                            }
-                        },
-                        WeaponPattern::SixAround => {
-                            let points = vec![
-                                Quat::from_axis_angle(Vec3::Z, 0.0) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/3.0) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/1.5) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI + PI/1.5) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI + PI/3.0) * velocity * 25.0,
-                            ];
-                            for p in points {
-                                shot_bullet(&mut commands, &textures, pl_transform.translation + p, p.normalize(), 1.0, 300.0);
-                            }
-                        },
-                        WeaponPattern::ManyAround => {
-                            let points = vec![
-                                Quat::from_axis_angle(Vec3::Z, 0.0) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/3.0) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/1.5) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI + PI/1.5) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI + PI/3.0) * velocity * 25.0,
-            
-                                Quat::from_axis_angle(Vec3::Z, PI/6.0) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/6.0+PI/3.0) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/6.0+PI/1.5) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/6.0+PI) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/6.0+PI + PI/1.5) * velocity * 25.0,
-                                Quat::from_axis_angle(Vec3::Z, PI/6.0+PI + PI/3.0) * velocity * 25.0,
-                            ];
-                            for p in points {
-                                shot_bullet(&mut commands, &textures, pl_transform.translation + p, p.normalize(), 1.0, 300.0);
-                            }
-                        },
-                    }
+                    fire_weapon(&mut commands, &textures, &mut pool, entity, Faction::Player, &pl_transform, &weapon);
                 }
             }
         }
     
     }
-}
\ No newline at end of file
+}
+
+fn check_death(
+    mut commands: Commands,
+    mut destroyed: EventWriter<TurretDestroyed>,
+    query: Query<(&Hp, Entity, &Transform), (Changed<Hp>, With<Turret>)>
+) {
+    for (hp, entity, transform) in query.iter() {
+        if hp.0 <= 0.0 {
+            info!("Turret destroyed");
+            destroyed.send(TurretDestroyed { turret: entity, position: transform.translation.truncate() });
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,11 @@
+/// Value following `flag` on the command line, e.g. `--replay run.replay`
+pub fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == flag)?;
+    args.next()
+}
+
+/// Whether `flag` is on the command line, e.g. `--friendly-fire`
+pub fn has_flag(flag: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == flag)
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use crate::cli::has_flag;
+use crate::GameState;
+
+/// Hit shape of an entity, in the pixels of its sprite texture: it grows and shrinks with the
+/// `Transform` scale, so a big enemy is as big to hit as it looks. Rotation is ignored.
+#[derive(Component, Clone, Copy, Debug)]
+pub enum Collider {
+    Circle { radius: f32 },
+    /// Axis-aligned box
+    Aabb { half_size: Vec2 },
+}
+
+/// A collider placed in the world
+#[derive(Clone, Copy, Debug)]
+enum Shape {
+    Circle { center: Vec2, radius: f32 },
+    Aabb { min: Vec2, max: Vec2 },
+}
+
+impl Collider {
+    pub fn circle(radius: f32) -> Self {
+        Collider::Circle { radius }
+    }
+
+    pub fn aabb(size: Vec2) -> Self {
+        Collider::Aabb { half_size: size / 2.0 }
+    }
+
+    fn shape(&self, transform: &Transform) -> Shape {
+        let center = transform.translation.truncate();
+        let scale = transform.scale.truncate().abs();
+        match *self {
+            Collider::Circle { radius } => Shape::Circle { center, radius: radius * scale.max_element() },
+            Collider::Aabb { half_size } => {
+                let half_size = half_size * scale;
+                Shape::Aabb { min: center - half_size, max: center + half_size }
+            }
+        }
+    }
+
+    /// Distance from the center to the farthest point of the collider at `transform`
+    pub fn extent(&self, transform: &Transform) -> f32 {
+        match self.shape(transform) {
+            Shape::Circle { radius, .. } => radius,
+            Shape::Aabb { min, max } => ((max - min) / 2.0).length(),
+        }
+    }
+
+    /// Whether this collider at `transform` touches `other` at `other_transform`
+    pub fn overlaps(&self, transform: &Transform, other: &Collider, other_transform: &Transform) -> bool {
+        match (self.shape(transform), other.shape(other_transform)) {
+            (Shape::Circle { center: a, radius: ra }, Shape::Circle { center: b, radius: rb }) => {
+                a.distance_squared(b) <= (ra + rb) * (ra + rb)
+            }
+            (Shape::Circle { center, radius }, Shape::Aabb { min, max })
+            | (Shape::Aabb { min, max }, Shape::Circle { center, radius }) => {
+                center.distance_squared(center.clamp(min, max)) <= radius * radius
+            }
+            (Shape::Aabb { min: min_a, max: max_a }, Shape::Aabb { min: min_b, max: max_b }) => {
+                min_a.cmple(max_b).all() && min_b.cmple(max_a).all()
+            }
+        }
+    }
+}
+
+/// Draws the outlines of all colliders when running with `--show-colliders`, F3 toggles them
+pub struct CollisionDebugPlugin;
+
+impl Plugin for CollisionDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowColliders(has_flag("--show-colliders")))
+            .add_systems(
+                Update,
+                (toggle_colliders, draw_colliders.run_if(|show: Res<ShowColliders>| show.0))
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct ShowColliders(pub bool);
+
+fn toggle_colliders(keys: Res<Input<KeyCode>>, mut show: ResMut<ShowColliders>) {
+    if keys.just_pressed(KeyCode::F3) {
+        show.0 = !show.0;
+    }
+}
+
+fn draw_colliders(mut gizmos: Gizmos, query: Query<(&Collider, &Transform)>) {
+    for (collider, transform) in query.iter() {
+        match collider.shape(transform) {
+            Shape::Circle { center, radius } => {
+                gizmos.circle_2d(center, radius, Color::LIME_GREEN);
+            }
+            Shape::Aabb { min, max } => {
+                gizmos.rect_2d((min + max) / 2.0, 0.0, max - min, Color::LIME_GREEN);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32, y: f32) -> Transform {
+        Transform::from_xyz(x, y, 0.0)
+    }
+
+    #[test]
+    fn circles() {
+        let circle = Collider::circle(10.0);
+        assert!(circle.overlaps(&at(0.0, 0.0), &circle, &at(20.0, 0.0)));
+        assert!(!circle.overlaps(&at(0.0, 0.0), &circle, &at(20.1, 0.0)));
+    }
+
+    #[test]
+    fn boxes() {
+        let square = Collider::aabb(Vec2::splat(10.0));
+        assert!(square.overlaps(&at(0.0, 0.0), &square, &at(10.0, 10.0)));
+        assert!(!square.overlaps(&at(0.0, 0.0), &square, &at(10.0, 10.1)));
+    }
+
+    #[test]
+    fn circle_and_box_either_way() {
+        let circle = Collider::circle(5.0);
+        let square = Collider::aabb(Vec2::splat(10.0));
+        // Near the corner of the box the circle is farther than on its sides
+        assert!(circle.overlaps(&at(9.0, 0.0), &square, &at(0.0, 0.0)));
+        assert!(!circle.overlaps(&at(9.0, 9.0), &square, &at(0.0, 0.0)));
+        assert!(square.overlaps(&at(0.0, 0.0), &circle, &at(9.0, 0.0)));
+        assert!(!square.overlaps(&at(0.0, 0.0), &circle, &at(9.0, 9.0)));
+    }
+
+    #[test]
+    fn colliders_scale_with_the_transform() {
+        let circle = Collider::circle(10.0);
+        let big = at(0.0, 0.0).with_scale(Vec3::splat(2.0));
+        assert!(circle.overlaps(&big, &circle, &at(30.0, 0.0)));
+        assert!(!circle.overlaps(&at(0.0, 0.0), &circle, &at(30.0, 0.0)));
+        assert_eq!(circle.extent(&big), 20.0);
+    }
+}
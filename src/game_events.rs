@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+
+use crate::characters::enemy_kinds::EnemyKind;
+use crate::characters::player::Weapon;
+
+/// What happened in a run, sent by the gameplay systems during the simulation step.
+/// Stats, audio, achievements and the UI read these instead of reaching into gameplay code.
+/// Systems in `Update` see the events of every step of the frame.
+pub struct GameEventsPlugin;
+
+impl Plugin for GameEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnemyKilled>()
+            .add_event::<ChestOpened>()
+            .add_event::<PlayerDamaged>()
+            .add_event::<TurretPlaced>()
+            .add_event::<TurretDestroyed>()
+            .add_event::<WeaponSwapped>()
+            .add_event::<PhaseChanged>()
+            .add_event::<ShotFired>()
+            .add_event::<BulletLanded>();
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct EnemyKilled {
+    pub enemy: Entity,
+    pub kind: EnemyKind,
+    pub position: Vec2,
+    /// Whoever dealt the last hit, or applied the effect that dealt it
+    pub killer: Option<Entity>,
+    /// Name of the weapon of the last hit
+    pub weapon: Option<String>,
+}
+
+#[derive(Clone)]
+pub enum Loot {
+    Energy(u32),
+    /// Offered for a while, see `TemporaryItems`
+    Weapon(Weapon),
+    /// Put straight in the empty turret slot
+    Turret(Weapon),
+    /// Offered for a while, the turret slot was taken
+    TurretOffer(Weapon),
+}
+
+#[derive(Event, Clone)]
+pub struct ChestOpened {
+    pub chest: Entity,
+    pub position: Vec2,
+    pub loot: Vec<Loot>,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerDamaged {
+    /// Damage that reached `Hp`
+    pub amount: f32,
+    /// Damage the shield took
+    pub absorbed: f32,
+    pub hp_left: f32,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct TurretPlaced {
+    pub turret: Entity,
+    pub weapon: String,
+    pub position: Vec2,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TurretDestroyed {
+    pub turret: Entity,
+    pub position: Vec2,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeaponSlot {
+    /// The weapon the player shoots
+    Hand,
+    /// The turret waiting to be placed
+    Turret,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct WeaponSwapped {
+    pub slot: WeaponSlot,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+/// Day broke or night fell
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PhaseChanged {
+    pub is_night: bool,
+    pub day: i32,
+}
+
+/// The player pulled the trigger
+#[derive(Event, Clone, Debug)]
+pub struct ShotFired {
+    pub weapon: String,
+    /// Projectiles of the whole shot, bursts included
+    pub projectiles: u32,
+    /// Energy it took
+    pub energy: i32,
+}
+
+/// A bullet hit its first target, later hits of the same bullet aren't sent
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BulletLanded {
+    pub bullet: Entity,
+    pub owner: Entity,
+}
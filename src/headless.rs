@@ -0,0 +1,176 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{aim_angle, Actions, ActionsSet};
+use crate::characters::enemy::Enemy;
+use crate::characters::player::{Inventoty, Player, TemporaryItems};
+use crate::collision::Collider;
+use crate::interactive_items::chest::Chest;
+use crate::loading::TextureAssets;
+use crate::map::DayNight;
+use crate::rng::{reseed, GameRng, NextSeed};
+use crate::simulation::{SimulationPlugin, SimulationTime, SIMULATION_STEP};
+use crate::GameState;
+
+/// The bot shoots at enemies closer than this
+const BOT_FIRE_RANGE: f32 = 400.0;
+/// The bot backs off from enemies closer than this
+const BOT_PANIC_RANGE: f32 = 200.0;
+
+/// Plays the game without a window, GPU, egui or audio, for soak tests on CI.
+/// Add it next to `MinimalPlugins` instead of `GamePlugin`; a bot plays `runs` runs back to back
+/// and the app exits when they are done. Runs use `seed`, `seed + 1`, ... or random seeds.
+///
+/// See `examples/headless.rs`.
+pub struct HeadlessGamePlugin {
+    pub runs: u32,
+    pub seed: Option<u64>,
+}
+
+impl Default for HeadlessGamePlugin {
+    fn default() -> Self {
+        Self { runs: 1, seed: None }
+    }
+}
+
+impl Plugin for HeadlessGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((AssetPlugin::default(), HierarchyPlugin, TransformPlugin, SimulationPlugin))
+            .add_asset::<TextureAtlas>()
+            // Nothing is drawn, so nothing is loaded and the menus are skipped
+            .init_resource::<TextureAssets>()
+            .insert_resource(NextState(Some(GameState::Playing)))
+            // One simulation step per frame, computed as fast as the machine allows
+            .insert_resource(TimeUpdateStrategy::ManualDuration(SIMULATION_STEP))
+            .insert_resource(HeadlessRuns {
+                remaining: self.runs,
+                seed: self.seed,
+                ..default()
+            })
+            .add_systems(OnEnter(GameState::Playing), start_run.after(reseed))
+            .add_systems(
+                PreUpdate,
+                autopilot.in_set(ActionsSet).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), end_run)
+            .add_systems(OnEnter(GameState::MenuDeath), finish_run(RunOutcome::Died))
+            .add_systems(OnEnter(GameState::MenuWin), finish_run(RunOutcome::Survived));
+
+        if let Some(seed) = self.seed {
+            app.insert_resource(NextSeed(Some(seed)));
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum RunOutcome {
+    Died,
+    Survived,
+}
+
+/// What the summary at the end needs, each run is logged as it ends
+#[derive(Clone, Copy, Debug)]
+struct RunResult {
+    outcome: RunOutcome,
+    /// The day the run ended on
+    day: i32,
+}
+
+#[derive(Resource, Default)]
+struct HeadlessRuns {
+    remaining: u32,
+    seed: Option<u64>,
+    /// The run that just left `GameState::Playing`, its outcome is known in the next state
+    ended: Option<(u64, i32, f64)>,
+    results: Vec<RunResult>,
+}
+
+fn start_run(rng: Res<GameRng>, runs: Res<HeadlessRuns>) {
+    info!("Headless run {} started, seed {}", runs.results.len() + 1, rng.seed);
+}
+
+fn end_run(time: Res<SimulationTime>, rng: Res<GameRng>, day_night: Res<DayNight>, mut runs: ResMut<HeadlessRuns>) {
+    runs.ended = Some((rng.seed, day_night.day, time.elapsed_seconds_f64()));
+}
+
+fn finish_run(
+    outcome: RunOutcome,
+) -> impl Fn(ResMut<HeadlessRuns>, ResMut<NextSeed>, ResMut<NextState<GameState>>, EventWriter<AppExit>) {
+    move |mut runs, mut next_seed, mut state, mut exit| {
+        let Some((seed, day, time)) = runs.ended.take() else {
+            return;
+        };
+        info!("Headless run {}: {:?} on day {} after {:.0}s, seed {}", runs.results.len() + 1, outcome, day, time, seed);
+        runs.results.push(RunResult { outcome, day });
+        runs.remaining = runs.remaining.saturating_sub(1);
+
+        if runs.remaining > 0 {
+            next_seed.0 = runs.seed.map(|base| base.wrapping_add(runs.results.len() as u64));
+            state.set(GameState::Playing);
+        } else {
+            let survived = runs.results.iter().filter(|r| r.outcome == RunOutcome::Survived).count();
+            let average_day = runs.results.iter().map(|r| r.day as f32).sum::<f32>() / runs.results.len() as f32;
+            info!("Headless runs done: {} of {} survived, average last day {:.1}", survived, runs.results.len(), average_day);
+            exit.send(AppExit);
+        }
+    }
+}
+
+/// A simple bot: loots the closest chest, shoots the closest enemy, backs off when it gets close,
+/// takes every item it's offered and drops the turret when the night gets dangerous
+fn autopilot(
+    time: Res<SimulationTime>,
+    mut actions: ResMut<Actions>,
+    day_night: Res<DayNight>,
+    temporary: Res<TemporaryItems>,
+    inventory: Res<Inventoty>,
+    player: Query<(&Transform, &Collider), With<Player>>,
+    enemies: Query<&Transform, With<Enemy>>,
+    chests: Query<(&Transform, &Collider, &Chest)>,
+) {
+    let Ok((player, player_collider)) = player.get_single() else {
+        return;
+    };
+    let position = player.translation.truncate();
+    let enemy = closest(position, enemies.iter().map(|t| t.translation.truncate()));
+    let chest = closest(
+        position,
+        chests.iter().filter(|(_, _, chest)| !chest.opened).map(|(t, _, _)| t.translation.truncate()),
+    );
+
+    let mut fire = false;
+    if let Some(enemy) = enemy {
+        let direction = enemy - position;
+        if direction != Vec2::ZERO {
+            actions.mouse_angle = aim_angle(direction);
+            actions.aim_point = enemy;
+        }
+        fire = direction.length() < BOT_FIRE_RANGE;
+    }
+
+    let threat = enemy.filter(|enemy| enemy.distance(position) < BOT_PANIC_RANGE);
+    let movement = match (threat, chest) {
+        (Some(enemy), _) => (position - enemy).normalize_or_zero(),
+        (None, Some(chest)) => (chest - position).normalize_or_zero(),
+        (None, None) => Vec2::ZERO,
+    };
+    actions.player_movement = (movement != Vec2::ZERO).then_some(movement);
+
+    // Verbs act on the press, so they are released every other frame
+    let near_chest = chests
+        .iter()
+        .any(|(transform, collider, chest)| !chest.opened && collider.overlaps(transform, player_collider, player));
+    let interact = near_chest && !actions.interact.pressed;
+    let accept = temporary.offer_active(time.elapsed_seconds_f64()) && !actions.accept_offer.pressed;
+    let place = day_night.is_night && threat.is_some() && inventory.turret.is_some() && !actions.place_turret.pressed;
+    actions.fire.update(fire);
+    actions.interact.update(interact);
+    actions.accept_offer.update(accept);
+    actions.place_turret.update(place);
+}
+
+fn closest(position: Vec2, points: impl Iterator<Item = Vec2>) -> Option<Vec2> {
+    points.min_by(|a, b| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+}
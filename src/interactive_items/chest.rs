@@ -1,22 +1,16 @@
-use bevy::{prelude::{Component, Res, Input, KeyCode, Query, Transform, With, Handle, Image, ResMut}, time::Time};
-use rand::{Rng, seq::SliceRandom};
+use bevy::prelude::{Component, Res, Query, Transform, With, Handle, Image, ResMut, Entity, EventWriter};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{loading::TextureAssets, characters::player::{Player, Energy, Weapon, WeaponPattern, TemporaryItems, Inventoty}, ui::UiLog};
-use lazy_static::lazy_static;
+use crate::collision::Collider;
+use crate::rng::GameRng;
+use crate::simulation::SimulationTime;
 
-lazy_static! {
-    pub static ref WEAPONS : Vec<Weapon> =vec![
-        Weapon { name: "Fast Eradictor".into(), pattern: WeaponPattern::Single, cd: 0.1, from_shot: 0.0},
-        Weapon { name: "Double Penetrator".into(), pattern: WeaponPattern::TwoShot, cd: 0.15, from_shot: 0.0},
-        Weapon { name: "Six Paths Destroyer".into(), pattern: WeaponPattern::SixRay, cd: 0.4, from_shot: 0.0},
-        Weapon { name: "Protector MK2".into(), pattern: WeaponPattern::SixAround, cd: 0.3, from_shot: 0.0},
-        Weapon { name: "Protector MK4".into(), pattern: WeaponPattern::ManyAround, cd: 0.2, from_shot: 0.0},
-    ];
-}
+use crate::{actions::Actions, game_events::{ChestOpened, Loot}, loading::TextureAssets, characters::{arsenal::Arsenal, player::{Player, Energy, TemporaryItems, Inventoty}}};
 
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum ItemType {
     Energy,
     Weapon,
@@ -24,13 +18,14 @@ pub enum ItemType {
 }
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Item {
     pub item_type: ItemType,
     pub count: u32
 }
 
-#[derive(Component, Default)]
+/// Saved as is, with what it still contains
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
 pub struct Chest {
     items: Vec<Item>,
     pub opened: bool
@@ -44,14 +39,14 @@ impl Chest {
         res
     }
     
-    pub fn generate(&mut self) {
+    pub fn generate(&mut self, rng: &mut impl Rng) {
         //energy
         let energy = Item {
-            count: rand::thread_rng().gen_range(0..100),
+            count: rng.gen_range(0..100),
             item_type: ItemType::Energy
         };
         self.items.push(energy);
-        let r = rand::random::<f32>();
+        let r = rng.gen::<f32>();
         if r >= 0.8 {
             let item = Item {
                 count: 1,
@@ -69,54 +64,52 @@ impl Chest {
 }
 
 pub fn open_chest(
-    time: Res<Time>,
+    time: Res<SimulationTime>,
     mut temporary: ResMut<TemporaryItems>,
     mut inventory: ResMut<Inventoty>,
-    mut ui_log: ResMut<UiLog>,
     textures: Res<TextureAssets>,
-    keys : Res<Input<KeyCode>>,
-    mut query_player: Query<(&Transform, &mut Energy), With<Player>>,
-    mut query: Query<(&Transform, &mut Chest, &mut Handle<Image>)>
+    actions: Res<Actions>,
+    arsenal: Res<Arsenal>,
+    mut rng: ResMut<GameRng>,
+    mut opened: EventWriter<ChestOpened>,
+    mut query_player: Query<(&Transform, &Collider, &mut Energy), With<Player>>,
+    mut query: Query<(Entity, &Transform, &Collider, &mut Chest, &mut Handle<Image>)>
 ) {
-    let (player_tr, mut energy) = query_player.single_mut();
-    let player_pos = player_tr.translation;
-    for (transform, mut chest, mut texture) in query.iter_mut() {
-        if !chest.opened && player_pos.distance_squared(transform.translation) <= 30.0 * 30.0 {
-            if keys.just_pressed(KeyCode::F){
+    let (player_tr, player_collider, mut energy) = query_player.single_mut();
+    for (entity, transform, collider, mut chest, mut texture) in query.iter_mut() {
+        if !chest.opened && collider.overlaps(transform, player_collider, player_tr) {
+            if actions.interact.just_pressed {
                 *texture = textures.texture_chest_opened.clone();
-                  let times = time.elapsed_seconds_f64();
                 let items = chest.get_items();
-                let mut message = "New items: ".to_string();
+                let mut loot = Vec::new();
                 for item in items.iter() {
                     match item.item_type {
                         ItemType::Energy => {
                             energy.0 += item.count as i32;
                             energy.0 = energy.0.clamp(0, 512);
-                            let add = format!("{} energy", item.count);
-                            message += &add;
+                            loot.push(Loot::Energy(item.count));
                         },
                         ItemType::Weapon => {
-                            let random_weapon = WEAPONS.choose(&mut rand::thread_rng()).unwrap();
+                            let random_weapon = arsenal.random_drop(&mut rng.loot);
                             temporary.weapon = Some(random_weapon.clone());
                             temporary.timestamp = time.elapsed_seconds_f64();
-                            message += &format!("Found {}, press R to change", random_weapon.name);
+                            loot.push(Loot::Weapon(random_weapon.clone()));
                         },
                         ItemType::Turret => {
-                            let random_weapon = WEAPONS.choose(&mut rand::thread_rng()).unwrap();
+                            let random_weapon = arsenal.random_drop(&mut rng.loot);
                             if inventory.turret.is_none(){
                                 inventory.turret = Some(random_weapon.clone());
-                                message += &format!("Found turret {}", random_weapon.name);
+                                loot.push(Loot::Turret(random_weapon.clone()));
                             } else {
                                 temporary.turret = Some(random_weapon.clone());
                                 temporary.timestamp = time.elapsed_seconds_f64();
-                                message += &format!("New turret {}, press R to change", random_weapon.name);
+                                loot.push(Loot::TurretOffer(random_weapon.clone()));
                             }
 
                         },
                     }
                 }
-                ui_log.last_message = message;
-                ui_log.message_time_stamp = times;
+                opened.send(ChestOpened { chest: entity, position: transform.translation.truncate(), loot });
                 break;
             }
         }
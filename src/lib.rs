@@ -10,27 +10,43 @@ mod interactive_items;
 mod ui;
 mod menu_death;
 mod menu_win;
+mod menu_controls;
+mod storage;
+mod cli;
+mod rng;
+mod replay;
+mod headless;
+mod simulation;
+mod run_stats;
+mod run_history;
+mod save_game;
+pub mod collision;
+pub mod game_events;
+pub mod spatial_grid;
 use actions::ActionsPlugin;
 use audio::InternalAudioPlugin;
 use bevy_egui::EguiPlugin;
-use characters::bullets::BulletPlugin;
+use collision::CollisionDebugPlugin;
 
-use characters::cleaner::CleanerPlugin;
-use characters::turret::TurretPlugin;
 use loading::LoadingPlugin;
 use menu::MenuPlugin;
-use characters::player::PlayerPlugin;
-use characters::enemy::EnemyPlugin;
-use map::MapPlugin;
+use characters::player::check_resolution;
 
 use bevy::app::App;
 // #[cfg(debug_assertions)]
 // use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
+use menu_controls::MenuControlsPlugin;
 use menu_death::MenuDeathPlugin;
+use replay::ReplayPlugin;
+use run_history::RunHistoryPlugin;
+use save_game::SaveGamePlugin;
 use menu_win::MenuWinPlugin;
+use simulation::SimulationPlugin;
 use ui::UiPlugin;
 
+pub use headless::HeadlessGamePlugin;
+
 // This example game uses States to separate logic
 // See https://bevy-cheatbook.github.io/programming/states.html
 // Or https://github.com/bevyengine/bevy/blob/main/examples/ecs/state.rs
@@ -45,28 +61,31 @@ enum GameState {
     Menu,
     MenuDeath,
     MenuWin,
+    MenuControls,
 }
 
+/// Everything needed to play, with windows, input, rendering, egui and audio
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_state::<GameState>().add_plugins((
-            LoadingPlugin,
-            MenuPlugin,
-            MenuDeathPlugin,
-            MenuWinPlugin,
-            CleanerPlugin,
-            ActionsPlugin,
-            InternalAudioPlugin,
-            PlayerPlugin,
-            EnemyPlugin,
-            TurretPlugin,
-            MapPlugin,
-            BulletPlugin,
-            EguiPlugin,
-            UiPlugin
-        ));
+        app.add_plugins(SimulationPlugin)
+            .add_plugins((
+                LoadingPlugin,
+                MenuPlugin,
+                MenuDeathPlugin,
+                MenuWinPlugin,
+                MenuControlsPlugin,
+                ActionsPlugin,
+                ReplayPlugin,
+                RunHistoryPlugin,
+                SaveGamePlugin,
+                InternalAudioPlugin,
+                EguiPlugin,
+                UiPlugin,
+                CollisionDebugPlugin,
+            ))
+            .add_systems(Update, check_resolution.run_if(in_state(GameState::Playing)));
 
         // #[cfg(debug_assertions)]
         // {
@@ -3,6 +3,8 @@ use bevy::{prelude::*};
 use bevy_asset_loader::prelude::*;
 use bevy_kira_audio::AudioSource;
 
+use crate::characters::arsenal::WeaponRoster;
+
 pub struct LoadingPlugin;
 
 /// This plugin loads all assets using [`AssetLoader`] from a third party bevy plugin
@@ -16,7 +18,8 @@ impl Plugin for LoadingPlugin {
         )
            .add_collection_to_loading_state::<_, FontAssets>(GameState::Loading)
         .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading);
+        .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading)
+        .add_collection_to_loading_state::<_, WeaponAssets>(GameState::Loading);
     }
 }
 
@@ -29,13 +32,21 @@ pub struct FontAssets {
     pub fira_sans: Handle<Font>,
 }
 
+/// Keeps the weapon roster loaded, so it is reloaded when the file changes (see `ArsenalPlugin`)
+#[derive(AssetCollection, Resource)]
+pub struct WeaponAssets {
+    #[asset(path = "data/arsenal.weapons.ron")]
+    pub roster: Handle<WeaponRoster>,
+}
+
 #[derive(AssetCollection, Resource)]
 pub struct AudioAssets {
     #[asset(path = "audio/flying.ogg")]
     pub flying: Handle<AudioSource>,
 }
 
-#[derive(AssetCollection, Resource)]
+/// `Default` gives empty handles, for running the game without loading anything (see `HeadlessGamePlugin`)
+#[derive(AssetCollection, Resource, Default)]
 pub struct TextureAssets {
     #[asset(path = "textures/bevy.png")]
     pub texture_bevy: Handle<Image>,
@@ -55,6 +66,18 @@ pub struct TextureAssets {
     #[asset(path = "textures/enemy.png")]
     pub texture_enemy: Handle<Image>,
 
+    #[asset(path = "textures/enemy_charger.png")]
+    pub texture_enemy_charger: Handle<Image>,
+
+    #[asset(path = "textures/enemy_spitter.png")]
+    pub texture_enemy_spitter: Handle<Image>,
+
+    #[asset(path = "textures/enemy_swarmer.png")]
+    pub texture_enemy_swarmer: Handle<Image>,
+
+    #[asset(path = "textures/enemy_tank.png")]
+    pub texture_enemy_tank: Handle<Image>,
+
     #[asset(path = "textures/bullet.png")]
     pub texture_bullet: Handle<Image>,
     
@@ -1,12 +1,15 @@
 use std::f32::consts::PI;
 
-use bevy::{prelude::{Plugin, Component, OnEnter, Commands, Res, Transform, Resource, Entity, ResMut, Update, Query, With, Camera2d, info, IntoSystemConfigs, in_state, DespawnRecursiveExt, Color, PointLightBundle, Without, EventWriter, Event, Vec3, Quat}, sprite::{SpriteBundle, Sprite, TextureAtlasSprite}, math::vec3, utils::HashMap, time::Time};
+use bevy::{prelude::{Plugin, Component, OnEnter, FixedUpdate, Commands, Res, Transform, Resource, Entity, ResMut, Update, Query, With, info, IntoSystemConfigs, in_state, DespawnRecursiveExt, Color, PointLightBundle, Without, EventWriter, Vec2, Vec3, Quat, EulerRot}, sprite::{SpriteBundle, Sprite, TextureAtlasSprite}, math::vec3, utils::HashMap};
 
-use crate::{loading::TextureAssets, GameState, characters::{enemy::Enemy, bullets::Bullet}, interactive_items::chest::{Chest, open_chest}};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{collision::Collider, game_events::PhaseChanged, loading::TextureAssets, rng::GameRng, simulation::{SimulationSet, SimulationTime}, GameState, characters::{arsenal::Arsenal, base_character::Hp, enemy::Enemy, bullets::Bullet, player::{Player, Weapon}, turret::{spawn_turret, Turret}}, interactive_items::chest::{Chest, open_chest}};
 
 pub struct MapPlugin;
 
-#[derive(Resource)]
+#[derive(Resource, Clone, Serialize, Deserialize)]
 pub struct DayNight {
     pub current_day_time: f32,
     pub current_night_time: f32,
@@ -16,36 +19,103 @@ pub struct DayNight {
     pub day: i32
 }
 
+/// The start of a run, mid-day of the first day
+impl Default for DayNight {
+    fn default() -> Self {
+        Self {
+            current_day_time: 0.7,
+            current_night_time: 0.3,
+            full_day_time: 120.0,
+            time: 60.0,
+            is_night: false,
+            day: 1
+        }
+    }
+}
+
 #[derive(Component)]
 struct Ground;
 
+/// Side of a ground tile, a chunk of the map is the square under one tile
+pub const CHUNK_SIZE: f32 = 512.0;
+
 #[derive(Resource, Default)]
 pub struct Map {
     pub tiles: HashMap<(i32, i32), Entity>,
     pub last_position: (i32, i32),
-    pub chestes_spawned: Vec<(i32, i32)>
+    /// Every chunk generated so far. The record holds the content of chunks that are unloaded,
+    /// it's empty while the chunk is loaded and its content is in the world.
+    pub chunks: HashMap<(i32, i32), ChunkRecord>,
+}
+
+/// Chunk the point lies in, the one whose tile is drawn under it
+pub fn chunk_of(position: Vec2) -> (i32, i32) {
+    ((position.x / CHUNK_SIZE).round() as i32, (position.y / CHUNK_SIZE).round() as i32)
 }
 
-// Event
-#[derive(Event)]
-pub struct DayNightEvent(pub bool);
+/// Content of an unloaded chunk, respawned as it was when the chunk is loaded again
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ChunkRecord {
+    pub chests: Vec<StoredChest>,
+    pub turrets: Vec<StoredTurret>,
+}
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredChest {
+    pub position: Vec3,
+    /// Radians
+    pub rotation: f32,
+    pub chest: Chest,
+}
+
+impl StoredChest {
+    pub fn new(transform: &Transform, chest: &Chest) -> Self {
+        Self {
+            position: transform.translation,
+            rotation: transform.rotation.to_euler(EulerRot::XYZ).2,
+            chest: chest.clone(),
+        }
+    }
+
+    pub fn spawn(&self, commands: &mut Commands, textures: &TextureAssets, color: Color) {
+        let transform = Transform::from_translation(self.position)
+            .with_scale(Vec3::splat(0.25))
+            .with_rotation(Quat::from_rotation_z(self.rotation));
+        spawn_chest(commands, textures, self.chest.clone(), transform, color);
+    }
+}
+
+/// Turrets are kept by weapon name and looked up in the [`Arsenal`] when respawned
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredTurret {
+    pub position: Vec3,
+    pub hp: f32,
+    pub weapon: String,
+}
+
+impl StoredTurret {
+    pub fn new(transform: &Transform, hp: &Hp, weapon: &Weapon) -> Self {
+        Self { position: transform.translation, hp: hp.0, weapon: weapon.name.clone() }
+    }
+
+    pub fn spawn(&self, commands: &mut Commands, textures: &TextureAssets, arsenal: &Arsenal) {
+        let weapon = arsenal.get_or(&self.weapon, &arsenal.starting_turret);
+        let turret = spawn_turret(commands, textures, &weapon, self.position);
+        commands.entity(turret).insert(Hp(self.hp));
+    }
+}
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app
-        .add_event::<DayNightEvent>()
         .insert_resource(Map::default())
-        .insert_resource(DayNight {
-            current_day_time: 0.7,
-            current_night_time: 0.3,
-            full_day_time: 120.0,
-            time: 60.0,
-            is_night: false,
-            day: 1
-        })
+        .init_resource::<DayNight>()
         .add_systems(OnEnter(GameState::Playing), spawn_map)
-        .add_systems(Update, (open_chest, (check_map, day_night_cycle, day_night_coloring).chain()).run_if(in_state(GameState::Playing)))
+        .add_systems(FixedUpdate, (open_chest, check_map, day_night_cycle)
+            .chain()
+            .in_set(SimulationSet::World)
+            .run_if(in_state(GameState::Playing)))
+        .add_systems(Update, day_night_coloring.run_if(in_state(GameState::Playing)))
         ;
     }
 }
@@ -53,8 +123,8 @@ impl Plugin for MapPlugin {
 
 fn day_night_cycle(
     mut day_night: ResMut<DayNight>,
-    time: Res<Time>,
-    mut ev_daynight: EventWriter<DayNightEvent>,
+    time: Res<SimulationTime>,
+    mut phase_changed: EventWriter<PhaseChanged>,
 ) {
     day_night.time += time.delta_seconds();
     if day_night.time >= day_night.full_day_time {
@@ -64,12 +134,12 @@ fn day_night_cycle(
         day_night.current_day_time = (day_night.current_day_time - 0.05).clamp(0.1, 0.9);
         day_night.current_night_time = (day_night.current_night_time + 0.05).clamp(0.1, 0.9);
         day_night.is_night = false;
-        ev_daynight.send(DayNightEvent(false));
+        phase_changed.send(PhaseChanged { is_night: false, day: day_night.day });
     }
     else if !day_night.is_night && day_night.time >= day_night.current_day_time * day_night.full_day_time{
         
         day_night.is_night = true;
-        ev_daynight.send(DayNightEvent(true));
+        phase_changed.send(PhaseChanged { is_night: true, day: day_night.day });
         info!("New night");
     }
 }
@@ -109,27 +179,13 @@ fn day_night_coloring(
 pub fn spawn_map(
     mut command: Commands,
     mut map: ResMut<Map>,
-    textures: Res<TextureAssets>
+    mut rng: ResMut<GameRng>,
+    textures: Res<TextureAssets>,
+    arsenal: Res<Arsenal>,
 ) {
     for x in -2..3 {
         for y in -2..3 {
-            let position = vec3(
-                x as f32 * 512.0,
-                y as f32 * 512.0,
-                0.0
-            );
-            let id = command.spawn(
-                SpriteBundle{
-                    texture: textures.texture_ground.clone(),
-                    transform: Transform::from_translation(position),
-                    ..Default::default()
-                }
-            ).id();
-            map.tiles.insert((x,y), id);                
-            if !map.chestes_spawned.contains(&(x,y)){
-                map.chestes_spawned.push((x,y));
-                spawn_chests(&mut command, &textures, position, Color::WHITE);
-            }
+            load_chunk(&mut command, &textures, &arsenal, &mut map, &mut rng, (x, y), Color::WHITE);
         }
     }
     command.spawn(PointLightBundle {
@@ -142,11 +198,16 @@ pub fn check_map(
     day_night: Res<DayNight>,
     mut command: Commands,
     mut map: ResMut<Map>,
+    mut rng: ResMut<GameRng>,
     textures: Res<TextureAssets>,
-    query: Query<&Transform, With<Camera2d>>
+    arsenal: Res<Arsenal>,
+    query: Query<&Transform, With<Player>>,
+    query_chests: Query<(Entity, &Transform, &Chest)>,
+    query_turrets: Query<(Entity, &Transform, &Hp, &Weapon), With<Turret>>,
 ) {
-    let camera_position = query.single().translation;
-    let true_position = ((camera_position.x / 512.0) as i32, (camera_position.y / 512.0) as i32);
+    // Tiles follow the player rather than the camera, which trails behind depending on the frame rate
+    let player_position = query.single().translation;
+    let true_position = chunk_of(player_position.truncate());
     if true_position != map.last_position {
         map.last_position = true_position;
         // clean old
@@ -168,6 +229,20 @@ pub fn check_map(
             }
         }
 
+        // The content of the unloaded chunks goes to their records
+        for (entity, transform, chest) in query_chests.iter() {
+            if let Some(record) = unloaded_record(&mut map, transform) {
+                record.chests.push(StoredChest::new(transform, chest));
+                command.entity(entity).despawn_recursive();
+            }
+        }
+        for (entity, transform, hp, weapon) in query_turrets.iter() {
+            if let Some(record) = unloaded_record(&mut map, transform) {
+                record.turrets.push(StoredTurret::new(transform, hp, weapon));
+                command.entity(entity).despawn_recursive();
+            }
+        }
+
 
         // Spawn tile with needed color
         let light_intency;
@@ -188,60 +263,105 @@ pub fn check_map(
         let color = Color::rgb(current_color_vec.x, current_color_vec.y, current_color_vec.z);
         for pos in new_positions.iter() {
             if !map.tiles.contains_key(pos) {
-                let position = vec3(
-                    pos.0 as f32 * 512.0,
-                    pos.1 as f32 * 512.0,
-                    0.0
-                );
-                let id = command.spawn(
-                    SpriteBundle{
-                        texture: textures.texture_ground.clone(),
-                        transform: Transform::from_translation(position),
-                        sprite: Sprite {
-                            color: color,
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    }
-                ).id();
-                map.tiles.insert(*pos, id);
-                if !map.chestes_spawned.contains(pos){
-                    map.chestes_spawned.push(*pos);
-                    spawn_chests(&mut command, &textures, position, color)
-                }
+                load_chunk(&mut command, &textures, &arsenal, &mut map, &mut rng, *pos, color);
+            }
+        }
+    }
+}
 
+/// The record of the chunk `transform` is in, if that chunk has no tile anymore
+fn unloaded_record<'a>(map: &'a mut Map, transform: &Transform) -> Option<&'a mut ChunkRecord> {
+    let chunk = chunk_of(transform.translation.truncate());
+    if map.tiles.contains_key(&chunk) {
+        return None;
+    }
+    Some(map.chunks.entry(chunk).or_default())
+}
+
+/// Spawns the tile of `pos` with what the chunk held when it was unloaded, or new chests the first time
+fn load_chunk(
+    commands: &mut Commands,
+    textures: &Res<TextureAssets>,
+    arsenal: &Arsenal,
+    map: &mut Map,
+    rng: &mut GameRng,
+    pos: (i32, i32),
+    color: Color,
+) {
+    let position = vec3(
+        pos.0 as f32 * CHUNK_SIZE,
+        pos.1 as f32 * CHUNK_SIZE,
+        0.0
+    );
+    let id = commands.spawn(
+        SpriteBundle{
+            texture: textures.texture_ground.clone(),
+            transform: Transform::from_translation(position),
+            sprite: Sprite {
+                color: color,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    ).id();
+    map.tiles.insert(pos, id);
+    match map.chunks.get_mut(&pos) {
+        Some(record) => {
+            let record = std::mem::take(record);
+            for chest in record.chests.iter() {
+                chest.spawn(commands, textures, color);
+            }
+            for turret in record.turrets.iter() {
+                turret.spawn(commands, textures, arsenal);
             }
         }
+        None => {
+            map.chunks.insert(pos, ChunkRecord::default());
+            spawn_chests(commands, textures, rng, position, color);
+        }
     }
 }
 
 fn spawn_chests(
     commands: &mut Commands,
     textures: &Res<TextureAssets>,
+    rng: &mut GameRng,
     pos: Vec3,
     color: Color,
 ) {
-    let chest_num = 2 + (rand::random::<f32>() * 6.0) as i32;
+    let chest_num = 2 + (rng.map.gen::<f32>() * 6.0) as i32;
     for _ in 0..chest_num {
         let new_pos = pos + vec3(
-            pos.x - 128.0 + rand::random::<f32>() * 256.0,
-            pos.y - 128.0 + rand::random::<f32>() * 256.0,
+            -128.0 + rng.map.gen::<f32>() * 256.0,
+            -128.0 + rng.map.gen::<f32>() * 256.0,
             0.1
         );
         let mut chest = Chest::default();
-        chest.generate();
-        commands.spawn(
-            SpriteBundle {
-                texture: textures.texture_chest_closed.clone(),
-                transform: Transform::from_translation(new_pos)
-                    .with_scale(Vec3::splat(0.25))
-                    .with_rotation(Quat::from_axis_angle(Vec3::Z, PI*2.0* rand::random::<f32>())),
-                sprite: Sprite {
-                    color: color,
-                    ..Default::default()
-                },
-                ..Default::default()
-            }
-        ).insert(chest);
+        chest.generate(&mut rng.chests);
+        let transform = Transform::from_translation(new_pos)
+            .with_scale(Vec3::splat(0.25))
+            .with_rotation(Quat::from_axis_angle(Vec3::Z, PI*2.0* rng.map.gen::<f32>()));
+        spawn_chest(commands, textures, chest, transform, color);
     }
+}
+
+/// A chest on the ground, new or respawned from a [`StoredChest`]
+fn spawn_chest(commands: &mut Commands, textures: &TextureAssets, chest: Chest, transform: Transform, color: Color) {
+    let texture = if chest.opened {
+        textures.texture_chest_opened.clone()
+    } else {
+        textures.texture_chest_closed.clone()
+    };
+    commands.spawn(
+        SpriteBundle {
+            texture,
+            transform,
+            sprite: Sprite {
+                color: color,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    ).insert(chest)
+    .insert(Collider::circle(60.0));
 }
\ No newline at end of file
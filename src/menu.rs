@@ -1,6 +1,10 @@
 use crate::loading::FontAssets;
+use crate::rng::{seed_from_text, NextSeed};
+use crate::run_history::RunHistory;
+use crate::save_game::{ContinueRun, SaveGame};
 use crate::GameState;
 use bevy::prelude::*;
+use bevy_egui::{egui::{self, Color32, FontId, RichText}, EguiContexts};
 
 pub struct MenuPlugin;
 
@@ -9,12 +13,24 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ButtonColors>()
+            .init_resource::<SeedText>()
             .add_systems(OnEnter(GameState::Menu), setup_menu)
-            .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
+            .add_systems(Update, (click_play_button, seed_input).run_if(in_state(GameState::Menu)))
             .add_systems(OnExit(GameState::Menu), cleanup_menu);
     }
 }
 
+#[derive(Component)]
+enum MenuButton {
+    Play,
+    Continue,
+    Controls,
+}
+
+/// Contents of the seed field, empty for a random seed
+#[derive(Resource, Default)]
+struct SeedText(String);
+
 #[derive(Resource)]
 struct ButtonColors {
     normal: Color,
@@ -34,7 +50,13 @@ fn setup_menu(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
+    next_seed: Res<NextSeed>,
+    mut seed_text: ResMut<SeedText>,
+    history: Res<RunHistory>,
 ) {
+    // e.g. a seed from the command line
+    seed_text.0 = next_seed.0.map(|seed| seed.to_string()).unwrap_or_default();
+    let has_save = SaveGame::load().is_some();
     commands.spawn(Camera2dBundle::default());
     commands.spawn(NodeBundle {
         style: Style {
@@ -68,7 +90,8 @@ fn setup_menu(
                 },
                 background_color: button_colors.normal.into(),
                 ..Default::default()
-            }).with_children(|parent| {
+            }).insert(MenuButton::Play)
+            .with_children(|parent| {
                     parent.spawn(TextBundle::from_section(
                         "Play",
                         TextStyle {
@@ -79,6 +102,54 @@ fn setup_menu(
                     ));
                 });
 
+            if has_save {
+                parent.spawn(ButtonBundle {
+                    style: Style {
+                        width: Val::Px(180.0),
+                        height: Val::Px(50.0),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    background_color: button_colors.normal.into(),
+                    ..Default::default()
+                }).insert(MenuButton::Continue)
+                .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            "Continue",
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    });
+            }
+
+            parent.spawn(ButtonBundle {
+                style: Style {
+                    width: Val::Px(180.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::all(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                background_color: button_colors.normal.into(),
+                ..Default::default()
+            }).insert(MenuButton::Controls)
+            .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Controls",
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 40.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+                });
+
             parent.spawn(TextBundle::from_section(
                 "Your spaceship landed on some hostile planet. You have NO HOME here! You have no fuel, no food and you should wait 10 days before you will be rescued!",
                 TextStyle {
@@ -87,23 +158,68 @@ fn setup_menu(
                     color: Color::rgb(0.9, 0.9, 0.9),
                 },
             ));
+
+            if !history.runs.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    format!("High scores\n{}", history.high_score_table()),
+                    TextStyle {
+                        font: font_assets.fira_sans.clone(),
+                        font_size: 24.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                ).with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }));
+            }
         });
     });
 }
 
+fn seed_input(
+    mut contexts: EguiContexts,
+    mut seed_text: ResMut<SeedText>,
+    mut next_seed: ResMut<NextSeed>,
+) {
+    egui::Area::new("seed")
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -40.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Seed").font(FontId::proportional(28.0)).color(Color32::WHITE));
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut seed_text.0)
+                        .hint_text("random")
+                        .font(FontId::monospace(24.0))
+                        .desired_width(260.0),
+                );
+                if response.changed() {
+                    next_seed.0 = seed_from_text(&seed_text.0);
+                }
+            });
+        });
+}
+
 fn click_play_button(
+    mut commands: Commands,
     button_colors: Res<ButtonColors>,
     mut state: ResMut<NextState<GameState>>,
     mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor),
+        (&Interaction, &mut BackgroundColor, &MenuButton),
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (interaction, mut color) in &mut interaction_query {
+    for (interaction, mut color, button) in &mut interaction_query {
         match *interaction {
-            Interaction::Pressed => {
-                state.set(GameState::Playing);
-            }
+            Interaction::Pressed => match button {
+                MenuButton::Play => state.set(GameState::Playing),
+                MenuButton::Continue => {
+                    if let Some(save) = SaveGame::load() {
+                        commands.insert_resource(ContinueRun(save));
+                        state.set(GameState::Playing);
+                    }
+                }
+                MenuButton::Controls => state.set(GameState::MenuControls),
+            },
             Interaction::Hovered => {
                 *color = button_colors.hovered.into();
             }
@@ -114,8 +230,7 @@ fn click_play_button(
     }
 }
 
-fn cleanup_menu(mut commands: Commands, button: Query<Entity, With<Button>>, node: Query<Entity, With<Node>>,camera: Query<Entity, With<Camera2d>>) {
-    commands.entity(button.single()).despawn_recursive();
+fn cleanup_menu(mut commands: Commands, node: Query<Entity, With<Node>>,camera: Query<Entity, With<Camera2d>>) {
     commands.entity(camera.single()).despawn_recursive();
     node.for_each(|n|{
 
@@ -0,0 +1,218 @@
+use crate::actions::bindings::{Bindings, InputBinding};
+use crate::actions::game_control::GameControl;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, FontId, RichText},
+    EguiContexts,
+};
+
+pub struct MenuControlsPlugin;
+
+/// This plugin draws the rebinding screen
+/// The screen is only drawn during the State `GameState::MenuControls`, bindings are saved to disk when leaving it
+impl Plugin for MenuControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(GameState::MenuControls), setup_menu)
+            .add_systems(
+                Update,
+                (capture_binding, controls_menu)
+                    .chain()
+                    .run_if(in_state(GameState::MenuControls)),
+            )
+            .add_systems(OnExit(GameState::MenuControls), cleanup_menu);
+    }
+}
+
+/// Seconds to wait for an input before giving up. There is no cancel key, so every input can be bound.
+const REBIND_TIMEOUT: f64 = 5.0;
+
+/// The control waiting for a new input, if any
+#[derive(Resource, Default)]
+struct Rebinding {
+    control: Option<GameControl>,
+    append: bool,
+    /// `Time::elapsed_seconds_f64` when the wait started
+    started: f64,
+    /// Why the last input wasn't bound
+    refused: Option<String>,
+}
+
+impl Rebinding {
+    fn start(&mut self, control: GameControl, append: bool, now: f64) {
+        *self = Self {
+            control: Some(control),
+            append,
+            started: now,
+            refused: None,
+        };
+    }
+
+    fn seconds_left(&self, now: f64) -> f64 {
+        (self.started + REBIND_TIMEOUT - now).max(0.0)
+    }
+}
+
+/// The control that would be left without any input if `input` moved to `control`
+fn control_left_unbound(
+    bindings: &Bindings,
+    control: GameControl,
+    input: InputBinding,
+) -> Option<GameControl> {
+    bindings
+        .controls
+        .iter()
+        .find(|(other, inputs)| **other != control && inputs.as_slice() == [input])
+        .map(|(other, _)| *other)
+}
+
+fn setup_menu(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+}
+
+fn capture_binding(
+    time: Res<Time>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    pad_buttons: Res<Input<GamepadButton>>,
+) {
+    let Some(control) = rebinding.control else {
+        return;
+    };
+    if rebinding.seconds_left(time.elapsed_seconds_f64()) <= 0.0 {
+        rebinding.control = None;
+        return;
+    }
+    let input = keyboard
+        .get_just_pressed()
+        .next()
+        .map(|key| InputBinding::Key(*key))
+        .or_else(|| {
+            mouse
+                .get_just_pressed()
+                .next()
+                .map(|button| InputBinding::Mouse(*button))
+        })
+        .or_else(|| {
+            pad_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| InputBinding::Pad(button.button_type))
+        });
+
+    if let Some(input) = input {
+        if let Some(other) = control_left_unbound(&bindings, control, input) {
+            warn!(
+                "Not binding {} to {:?}, {:?} would have no input left",
+                input.label(),
+                control,
+                other
+            );
+            rebinding.refused = Some(format!(
+                "{} is the only input of \"{}\", bind something else to it first",
+                input.label(),
+                other.label()
+            ));
+            rebinding.control = None;
+            return;
+        }
+        // One input drives only one control
+        for inputs in bindings.controls.values_mut() {
+            inputs.retain(|i| *i != input);
+        }
+        let mut inputs = if rebinding.append {
+            bindings.get(control).to_vec()
+        } else {
+            Vec::new()
+        };
+        inputs.push(input);
+        bindings.set(control, inputs);
+        rebinding.control = None;
+    }
+}
+
+fn controls_menu(
+    time: Res<Time>,
+    mut contexts: EguiContexts,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+        ui.vertical_centered(|ui| {
+            ui.label(RichText::new("Controls").font(FontId::proportional(40.0)));
+        });
+        ui.add_space(20.0);
+
+        egui::Grid::new("bindings")
+            .striped(true)
+            .spacing([30.0, 10.0])
+            .show(ui, |ui| {
+                for control in GameControl::ALL {
+                    ui.label(RichText::new(control.label()).font(FontId::monospace(20.0)));
+                    let inputs: Vec<String> =
+                        bindings.get(control).iter().map(|b| b.label()).collect();
+                    ui.label(RichText::new(inputs.join(", ")).font(FontId::monospace(20.0)));
+                    if rebinding.control == Some(control) {
+                        let seconds_left =
+                            rebinding.seconds_left(time.elapsed_seconds_f64()).ceil();
+                        ui.label(
+                            RichText::new(format!(
+                                "Press a key, mouse or pad button ({}s)",
+                                seconds_left
+                            ))
+                            .font(FontId::monospace(16.0)),
+                        );
+                    } else {
+                        ui.horizontal(|ui| {
+                            if ui.button("Change").clicked() {
+                                rebinding.start(control, false, time.elapsed_seconds_f64());
+                            }
+                            if ui.button("Add").clicked() {
+                                rebinding.start(control, true, time.elapsed_seconds_f64());
+                            }
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if let Some(refused) = &rebinding.refused {
+            ui.add_space(10.0);
+            ui.label(
+                RichText::new(refused)
+                    .font(FontId::monospace(16.0))
+                    .color(egui::Color32::LIGHT_RED),
+            );
+        }
+        ui.add_space(20.0);
+        ui.horizontal(|ui| {
+            if ui.button("Defaults").clicked() {
+                *bindings = Bindings::default();
+            }
+            if ui.button("AZERTY").clicked() {
+                *bindings = Bindings::azerty();
+            }
+            if ui.button("Left-handed").clicked() {
+                *bindings = Bindings::left_handed();
+            }
+            if ui.button("Save and back").clicked() {
+                state.set(GameState::Menu);
+            }
+        });
+    });
+}
+
+fn cleanup_menu(
+    mut commands: Commands,
+    bindings: Res<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    camera: Query<Entity, With<Camera2d>>,
+) {
+    bindings.save();
+    *rebinding = Rebinding::default();
+    commands.entity(camera.single()).despawn_recursive();
+}
@@ -1,4 +1,5 @@
 use crate::loading::FontAssets;
+use crate::run_stats::RunStats;
 use crate::GameState;
 use bevy::prelude::*;
 
@@ -34,6 +35,7 @@ fn setup_menu(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
+    stats: Res<RunStats>,
 ) {
     commands.spawn(Camera2dBundle::default());
 
@@ -89,6 +91,18 @@ fn setup_menu(
                     color: Color::rgb(0.9, 0.9, 0.9),
                 },
             ));
+
+            parent.spawn(TextBundle::from_section(
+                stats.report(),
+                TextStyle {
+                    font: font_assets.fira_sans.clone(),
+                    font_size: 20.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ).with_style(Style {
+                margin: UiRect::all(Val::Px(20.0)),
+                ..default()
+            }));
         });
     });
 
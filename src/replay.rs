@@ -0,0 +1,364 @@
+use std::f32::consts::TAU;
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::actions::{reset_actions, ActionState, Actions, ActionsSet};
+use crate::cli::arg_value;
+use crate::rng::{reseed, GameRng, NextSeed};
+use crate::storage;
+use crate::GameState;
+
+pub const REPLAY_FILE: &str = "last_run.replay";
+
+const MAGIC: &[u8; 4] = b"PJRP";
+const VERSION: u16 = 1;
+const HEADER_SIZE: usize = 4 + 2 + 8 + 4;
+const FRAME_SIZE: usize = 4 + 2 + 2 + 8 + 1;
+
+pub struct ReplayPlugin;
+
+/// This plugin records the `Actions` of every frame of a run, together with the seed and the frame times,
+/// and writes them to [`REPLAY_FILE`] in the data directory when the run ends.
+/// Start the game with `--replay <file>` to play a recorded run back instead of reading the input.
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recorder>()
+            .add_systems(OnEnter(GameState::Playing), start_recording.after(reseed))
+            // The state changes after `PreUpdate`, but the simulation already steps on that frame,
+            // so its frame is recorded and replayed here
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (apply_replay, record_frame).chain().after(start_recording).after(reset_actions),
+            )
+            .add_systems(
+                PreUpdate,
+                (quantize_actions, apply_replay, record_frame)
+                    .chain()
+                    .after(ActionsSet)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Last, advance_replay.run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), (save_recording, stop_replay))
+            .add_systems(OnEnter(GameState::Menu), start_replay.run_if(resource_exists::<Replay>()));
+
+        if let Some(path) = arg_value("--replay") {
+            match Replay::load(Path::new(&path)) {
+                Ok(replay) => {
+                    info!("Replaying {} ({} frames)", path, replay.frames.len());
+                    app.insert_resource(replay);
+                }
+                Err(e) => warn!("Can't load replay {}: {}", path, e),
+            }
+        }
+    }
+}
+
+/// Everything the gameplay systems read from the input in one frame, as it is before the simulation steps of the frame.
+/// Together with the frame time it decides how many steps run, so the steps replay exactly.
+/// Movement and aim angle are quantized, and the live `Actions` are quantized the same way,
+/// so a replay sees exactly the values the recorded run saw.
+#[derive(Clone, Copy)]
+pub struct ReplayFrame {
+    delta_nanos: u32,
+    movement: [i8; 2],
+    angle: u16,
+    aim_point: [f32; 2],
+    verbs: u8,
+}
+
+impl ReplayFrame {
+    pub fn capture(delta: Duration, actions: &Actions) -> Self {
+        let movement = actions.player_movement.unwrap_or(Vec2::ZERO);
+        let quantize = |v: f32| (v.clamp(-1.0, 1.0) * 127.0).round() as i8;
+        let angle = (actions.mouse_angle.rem_euclid(TAU) / TAU * 65536.0).round() as u32 % 65536;
+
+        let mut verbs = 0;
+        let states = [actions.fire, actions.interact, actions.place_turret, actions.accept_offer];
+        for (i, state) in states.iter().enumerate() {
+            verbs |= (state.pressed as u8) << (i * 2);
+            verbs |= (state.just_pressed as u8) << (i * 2 + 1);
+        }
+
+        Self {
+            delta_nanos: delta.as_nanos().min(u32::MAX as u128) as u32,
+            movement: [quantize(movement.x), quantize(movement.y)],
+            angle: angle as u16,
+            aim_point: actions.aim_point.to_array(),
+            verbs,
+        }
+    }
+
+    pub fn apply(&self, actions: &mut Actions) {
+        let movement = Vec2::new(self.movement[0] as f32, self.movement[1] as f32) / 127.0;
+        actions.player_movement = (movement != Vec2::ZERO).then_some(movement);
+        actions.mouse_angle = self.angle as f32 / 65536.0 * TAU;
+        actions.aim_point = Vec2::from_array(self.aim_point);
+
+        let state = |i: usize| ActionState {
+            pressed: self.verbs & (1 << (i * 2)) != 0,
+            just_pressed: self.verbs & (1 << (i * 2 + 1)) != 0,
+        };
+        actions.fire = state(0);
+        actions.interact = state(1);
+        actions.place_turret = state(2);
+        actions.accept_offer = state(3);
+    }
+
+    pub fn delta(&self) -> Duration {
+        Duration::from_nanos(self.delta_nanos as u64)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.delta_nanos.to_le_bytes());
+        out.push(self.movement[0] as u8);
+        out.push(self.movement[1] as u8);
+        out.extend_from_slice(&self.angle.to_le_bytes());
+        out.extend_from_slice(&self.aim_point[0].to_le_bytes());
+        out.extend_from_slice(&self.aim_point[1].to_le_bytes());
+        out.push(self.verbs);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        Self {
+            delta_nanos: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            movement: [bytes[4] as i8, bytes[5] as i8],
+            angle: u16::from_le_bytes(bytes[6..8].try_into().unwrap()),
+            aim_point: [
+                f32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+                f32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+            ],
+            verbs: bytes[16],
+        }
+    }
+}
+
+/// The run being recorded right now
+#[derive(Resource, Default)]
+pub struct Recorder {
+    pub seed: u64,
+    pub frames: Vec<ReplayFrame>,
+    /// Runs that are replays themselves are not saved again
+    from_replay: bool,
+    /// Continued runs don't start from their seed alone, so they can't be replayed
+    pub resumed: bool,
+}
+
+impl Recorder {
+    /// The run is a replay being played back, not a new one
+    pub fn is_replay(&self) -> bool {
+        self.from_replay
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_SIZE + self.frames.len() * FRAME_SIZE);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in self.frames.iter() {
+            frame.write(&mut out);
+        }
+        out
+    }
+}
+
+/// A recorded run being played back
+#[derive(Resource)]
+pub struct Replay {
+    pub seed: u64,
+    pub frames: Vec<ReplayFrame>,
+    cursor: usize,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_SIZE || bytes[0..4] != MAGIC[..] {
+            return Err("not a replay file".into());
+        }
+        let version = u16::from_le_bytes(bytes[4..6].try_into().unwrap());
+        if version != VERSION {
+            return Err(format!("unsupported replay version {}", version));
+        }
+        let seed = u64::from_le_bytes(bytes[6..14].try_into().unwrap());
+        let count = u32::from_le_bytes(bytes[14..18].try_into().unwrap()) as usize;
+        let body = &bytes[HEADER_SIZE..];
+        if body.len() != count * FRAME_SIZE {
+            return Err(format!("expected {} frames, file is truncated", count));
+        }
+        let frames = body.chunks_exact(FRAME_SIZE).map(ReplayFrame::read).collect();
+        Ok(Self { seed, frames, cursor: 0 })
+    }
+}
+
+/// Skip the menu and start the recorded run, with the recorded frame time from the very first frame
+fn start_replay(
+    mut commands: Commands,
+    replay: Res<Replay>,
+    mut next_seed: ResMut<NextSeed>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    let Some(first) = replay.frames.first() else {
+        commands.remove_resource::<Replay>();
+        return;
+    };
+    next_seed.0 = Some(replay.seed);
+    commands.insert_resource(TimeUpdateStrategy::ManualDuration(first.delta()));
+    state.set(GameState::Playing);
+}
+
+pub fn start_recording(mut recorder: ResMut<Recorder>, rng: Res<GameRng>, replay: Option<Res<Replay>>) {
+    recorder.seed = rng.seed;
+    recorder.frames.clear();
+    recorder.from_replay = replay.is_some();
+    recorder.resumed = false;
+}
+
+fn quantize_actions(mut actions: ResMut<Actions>) {
+    let frame = ReplayFrame::capture(Duration::ZERO, &actions);
+    frame.apply(&mut actions);
+}
+
+fn apply_replay(replay: Option<Res<Replay>>, mut actions: ResMut<Actions>) {
+    if let Some(frame) = replay.and_then(|replay| replay.frames.get(replay.cursor).copied()) {
+        frame.apply(&mut actions);
+    }
+}
+
+fn record_frame(time: Res<Time>, actions: Res<Actions>, mut recorder: ResMut<Recorder>) {
+    recorder.frames.push(ReplayFrame::capture(time.delta(), &actions));
+}
+
+/// Feeds the next recorded frame time, it is picked up by the `Time` update at the start of the next frame
+fn advance_replay(mut commands: Commands, replay: Option<ResMut<Replay>>) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+    replay.cursor += 1;
+    if let Some(frame) = replay.frames.get(replay.cursor) {
+        commands.insert_resource(TimeUpdateStrategy::ManualDuration(frame.delta()));
+    } else {
+        info!("Replay finished");
+        commands.insert_resource(TimeUpdateStrategy::Automatic);
+        commands.remove_resource::<Replay>();
+    }
+}
+
+/// The run can end before the recording does, e.g. when something wasn't deterministic
+fn stop_replay(mut commands: Commands, replay: Option<Res<Replay>>) {
+    if let Some(replay) = replay {
+        info!("Run ended at frame {} of {} of the replay", replay.cursor, replay.frames.len());
+        commands.insert_resource(TimeUpdateStrategy::Automatic);
+        commands.remove_resource::<Replay>();
+    }
+}
+
+fn save_recording(recorder: Res<Recorder>) {
+    if recorder.from_replay || recorder.resumed || recorder.frames.is_empty() {
+        return;
+    }
+    let Some(path) = storage::data_file(REPLAY_FILE) else {
+        return;
+    };
+    match storage::write(&path, recorder.to_bytes()) {
+        Ok(()) => info!("Replay of the run saved to {:?}", path),
+        Err(e) => warn!("Can't save replay to {:?}: {}", path, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::characters::player::Player;
+    use crate::run_stats::RunStats;
+    use crate::HeadlessGamePlugin;
+
+    const SEED: u64 = 7;
+    /// Frame times that run zero, one and two simulation steps per frame
+    const FRAME_MILLIS: [u64; 5] = [5, 16, 17, 33, 9];
+    const FRAMES: usize = 240;
+
+    fn headless_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, HeadlessGamePlugin { runs: 1, seed: Some(SEED) }, ReplayPlugin));
+        app
+    }
+
+    fn outcome(app: &mut App) -> (Vec3, String) {
+        let position = app
+            .world
+            .query_filtered::<&Transform, With<Player>>()
+            .single(&app.world)
+            .translation;
+        (position, app.world.resource::<RunStats>().report())
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_run() {
+        let mut recording = headless_app();
+        for frame in 0..FRAMES {
+            let delta = Duration::from_millis(FRAME_MILLIS[frame % FRAME_MILLIS.len()]);
+            recording.insert_resource(TimeUpdateStrategy::ManualDuration(delta));
+            recording.update();
+        }
+        let recorded = outcome(&mut recording);
+        let bytes = recording.world.resource::<Recorder>().to_bytes();
+        assert_eq!(recording.world.resource::<Recorder>().frames.len(), FRAMES);
+
+        let replay = Replay::from_bytes(&bytes).unwrap();
+        let mut playback = headless_app();
+        playback.insert_resource(TimeUpdateStrategy::ManualDuration(replay.frames[0].delta()));
+        playback.insert_resource(replay);
+        for _ in 0..FRAMES {
+            playback.update();
+        }
+        assert_eq!(outcome(&mut playback), recorded);
+    }
+
+    #[test]
+    fn replay_bytes_round_trip() {
+        let actions = Actions {
+            player_movement: Some(Vec2::new(0.5, -1.0)),
+            mouse_angle: 1.0,
+            aim_point: Vec2::new(10.0, -20.0),
+            fire: ActionState { pressed: true, just_pressed: true },
+            accept_offer: ActionState { pressed: true, just_pressed: false },
+            ..default()
+        };
+        let frame = ReplayFrame::capture(Duration::from_millis(16), &actions);
+        let recorder = Recorder { seed: 42, frames: vec![frame; 3], ..default() };
+
+        let replay = Replay::from_bytes(&recorder.to_bytes()).unwrap();
+        assert_eq!(replay.seed, 42);
+        assert_eq!(replay.frames.len(), 3);
+        let mut applied = Actions::default();
+        replay.frames[2].apply(&mut applied);
+        assert_eq!(replay.frames[2].delta(), Duration::from_millis(16));
+        assert_eq!(applied.fire, actions.fire);
+        assert_eq!(applied.accept_offer, actions.accept_offer);
+        assert_eq!(applied.interact, ActionState::default());
+        assert_eq!(applied.aim_point, actions.aim_point);
+        assert!((applied.player_movement.unwrap() - Vec2::new(0.5, -1.0)).length() < 0.01);
+    }
+
+    #[test]
+    fn replay_rejects_bad_files() {
+        let mut bytes = Recorder { seed: 1, frames: Vec::new(), ..default() }.to_bytes();
+        assert!(Replay::from_bytes(b"nope").is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(Replay::from_bytes(&wrong_version).is_err());
+
+        // Claims a frame the file doesn't have
+        bytes[14..18].copy_from_slice(&1u32.to_le_bytes());
+        assert!(Replay::from_bytes(&bytes).is_err());
+    }
+}
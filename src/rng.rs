@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::cli::arg_value;
+use crate::map::spawn_map;
+use crate::GameState;
+
+pub struct RngPlugin;
+
+/// This plugin owns the gameplay randomness
+/// The generators are reseeded when a run starts, so the same seed always builds the same world.
+/// Start the game with `--seed <seed>` to pick the seed of the first run.
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>()
+            .insert_resource(NextSeed(arg_value("--seed").and_then(|s| seed_from_text(&s))))
+            .add_systems(OnEnter(GameState::Playing), reseed.before(spawn_map));
+    }
+}
+
+/// The only source of randomness for gameplay, never use `rand::random` or `thread_rng` in gameplay code.
+/// Every subsystem draws from its own stream, so an extra roll in one of them doesn't shift the others.
+#[derive(Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    /// Chest placement on new map tiles
+    pub map: ChaCha8Rng,
+    /// Chest contents
+    pub chests: ChaCha8Rng,
+    /// Items picked when a chest is opened
+    pub loot: ChaCha8Rng,
+    /// Enemy spawn positions and stats
+    pub enemies: ChaCha8Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let stream = |id: u64| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(id);
+            rng
+        };
+        Self {
+            seed,
+            map: stream(1),
+            chests: stream(2),
+            loot: stream(3),
+            enemies: stream(4),
+        }
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+/// Seed for the next run, a random one is picked if empty
+#[derive(Resource, Default)]
+pub struct NextSeed(pub Option<u64>);
+
+/// Numbers are used as is, any other text is hashed, so "daily-2024-05-01" is a valid seed too
+pub fn seed_from_text(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if let Ok(seed) = text.parse() {
+        return Some(seed);
+    }
+    Some(fnv1a(text.as_bytes()))
+}
+
+/// FNV-1a, stable across platforms and releases unlike the std hasher
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub fn reseed(mut next_seed: ResMut<NextSeed>, mut rng: ResMut<GameRng>) {
+    let seed = next_seed.0.take().unwrap_or_else(rand::random);
+    info!("Run seed: {}", seed);
+    *rng = GameRng::new(seed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_used_as_is() {
+        assert_eq!(seed_from_text("42"), Some(42));
+        assert_eq!(seed_from_text("  42 \n"), Some(42));
+    }
+
+    #[test]
+    fn text_is_hashed_the_same_every_time() {
+        let seed = seed_from_text("daily-2024-05-01");
+        assert_eq!(seed, Some(fnv1a(b"daily-2024-05-01")));
+        assert_eq!(seed, seed_from_text("daily-2024-05-01"));
+        assert_ne!(seed, seed_from_text("daily-2024-05-02"));
+    }
+
+    #[test]
+    fn empty_text_is_no_seed() {
+        assert_eq!(seed_from_text(""), None);
+        assert_eq!(seed_from_text("   "), None);
+    }
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use bevy_egui::{egui::{self, Color32, FontId, RichText}, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::characters::player::{Player, Weapon};
+use crate::headless::RunOutcome;
+use crate::replay::Recorder;
+use crate::rng::GameRng;
+use crate::run_stats::RunStats;
+use crate::storage;
+use crate::GameState;
+
+pub const HISTORY_FILE: &str = "run_history.ron";
+
+/// Bumped when [`RunRecord`] changes. Fields added later need a `#[serde(default)]`
+/// so files written by older versions still load.
+const HISTORY_VERSION: u32 = 1;
+
+/// Runs shown on the main menu
+pub const HIGH_SCORES: usize = 10;
+
+pub struct RunHistoryPlugin;
+
+/// This plugin appends every finished run to [`HISTORY_FILE`] in the data directory
+/// and asks for a name when the run makes it into the high scores
+impl Plugin for RunHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunHistory::load())
+            .init_resource::<PendingRun>()
+            .init_resource::<NameEntry>()
+            .add_systems(OnExit(GameState::Playing), capture_run)
+            .add_systems(OnEnter(GameState::MenuDeath), finish_run(RunOutcome::Died))
+            .add_systems(OnEnter(GameState::MenuWin), finish_run(RunOutcome::Survived))
+            .add_systems(
+                Update,
+                name_entry.run_if(in_state(GameState::MenuDeath).or_else(in_state(GameState::MenuWin))),
+            );
+    }
+}
+
+/// The game has a single difficulty for now, the field is there so records keep it once there are more
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    #[default]
+    Normal,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// Empty until the player enters one
+    #[serde(default)]
+    pub name: String,
+    pub seed: u64,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    pub days_survived: i32,
+    pub kills: u32,
+    /// Weapon in hand when the run ended
+    pub weapon: String,
+    pub outcome: RunOutcome,
+}
+
+impl RunRecord {
+    pub fn display_name(&self) -> &str {
+        if self.name.is_empty() {
+            "Anonymous"
+        } else {
+            &self.name
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    runs: Vec<RunRecord>,
+}
+
+/// Every finished run, oldest first
+#[derive(Resource, Default)]
+pub struct RunHistory {
+    pub runs: Vec<RunRecord>,
+    /// The file was written by a newer version of the game, it is not overwritten
+    read_only: bool,
+}
+
+impl RunHistory {
+    pub fn load() -> Self {
+        let Some(path) = storage::data_file(HISTORY_FILE) else {
+            return Self::default();
+        };
+        let Some(text) = storage::read_to_string(&path) else {
+            return Self::default();
+        };
+        match ron::from_str::<HistoryFile>(&text) {
+            Ok(file) => {
+                let read_only = file.version > HISTORY_VERSION;
+                if read_only {
+                    warn!("{:?} is from a newer version of the game ({}), new runs won't be saved", path, file.version);
+                }
+                Self { runs: file.runs, read_only }
+            }
+            Err(e) => {
+                warn!("Can't parse {:?}: {}, new runs won't be saved", path, e);
+                Self { runs: Vec::new(), read_only: true }
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if self.read_only {
+            return;
+        }
+        let Some(path) = storage::data_file(HISTORY_FILE) else {
+            return;
+        };
+        let file = HistoryFile { version: HISTORY_VERSION, runs: self.runs.clone() };
+        let text = match ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("Can't serialize run history: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = storage::write(&path, &text) {
+            warn!("Can't save run history to {:?}: {}", path, e);
+        }
+    }
+
+    /// Indices of the best runs, best first: most days survived, then most kills, then the earliest
+    pub fn high_scores(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.runs.len()).collect();
+        indices.sort_by(|&a, &b| {
+            let (a_run, b_run) = (&self.runs[a], &self.runs[b]);
+            b_run.days_survived.cmp(&a_run.days_survived)
+                .then(b_run.kills.cmp(&a_run.kills))
+                .then(a.cmp(&b))
+        });
+        indices.truncate(HIGH_SCORES);
+        indices
+    }
+
+    /// Top runs, one line each, for the main menu
+    pub fn high_score_table(&self) -> String {
+        self.high_scores()
+            .iter()
+            .enumerate()
+            .map(|(place, &index)| {
+                let run = &self.runs[index];
+                format!(
+                    "{}. {}: {} days, {} kills, {}, {:?}, seed {}",
+                    place + 1,
+                    run.display_name(),
+                    run.days_survived,
+                    run.kills,
+                    run.weapon,
+                    run.outcome,
+                    run.seed,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// The run that just left `GameState::Playing`, its outcome is known in the next state
+#[derive(Resource, Default)]
+struct PendingRun(Option<RunRecord>);
+
+/// Name typed for the run at `index` of the history, while it's a new high score
+#[derive(Resource, Default)]
+struct NameEntry {
+    index: Option<usize>,
+    text: String,
+}
+
+fn capture_run(
+    rng: Res<GameRng>,
+    stats: Res<RunStats>,
+    recorder: Res<Recorder>,
+    mut pending: ResMut<PendingRun>,
+    query: Query<&Weapon, With<Player>>,
+) {
+    // Replays are runs that were already recorded
+    if recorder.is_replay() {
+        pending.0 = None;
+        return;
+    }
+    pending.0 = Some(RunRecord {
+        name: String::new(),
+        seed: rng.seed,
+        difficulty: Difficulty::Normal,
+        days_survived: stats.days_survived,
+        kills: stats.total.kills(),
+        weapon: query.get_single().map(|weapon| weapon.name.clone()).unwrap_or_default(),
+        outcome: RunOutcome::Died,
+    });
+}
+
+fn finish_run(
+    outcome: RunOutcome,
+) -> impl Fn(ResMut<PendingRun>, ResMut<RunHistory>, ResMut<NameEntry>) {
+    move |mut pending, mut history, mut name_entry| {
+        *name_entry = NameEntry::default();
+        let Some(mut run) = pending.0.take() else {
+            return;
+        };
+        run.outcome = outcome;
+        history.runs.push(run);
+        history.save();
+
+        let index = history.runs.len() - 1;
+        if history.high_scores().contains(&index) {
+            name_entry.index = Some(index);
+        }
+    }
+}
+
+fn name_entry(
+    mut contexts: EguiContexts,
+    mut history: ResMut<RunHistory>,
+    mut name_entry: ResMut<NameEntry>,
+) {
+    let Some(index) = name_entry.index else {
+        return;
+    };
+    egui::Area::new("high_score")
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -40.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("New high score! Name").font(FontId::proportional(28.0)).color(Color32::WHITE));
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut name_entry.text)
+                        .char_limit(16)
+                        .font(FontId::monospace(24.0))
+                        .desired_width(260.0),
+                );
+                let entered = response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                let clicked = ui.button(RichText::new("Save").font(FontId::proportional(28.0))).clicked();
+                if (entered || clicked) && !name_entry.text.trim().is_empty() {
+                    if let Some(run) = history.runs.get_mut(index) {
+                        run.name = name_entry.text.trim().to_string();
+                    }
+                    history.save();
+                    *name_entry = NameEntry::default();
+                }
+            });
+        });
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::characters::player::Player;
+use crate::characters::turret::Turret;
+use crate::game_events::{BulletLanded, ChestOpened, EnemyKilled, Loot, PlayerDamaged, ShotFired, TurretPlaced};
+use crate::map::DayNight;
+use crate::save_game::ContinueRun;
+use crate::simulation::SimulationSet;
+use crate::GameState;
+
+pub struct RunStatsPlugin;
+
+/// This plugin counts what happens in a run from the gameplay events, for the end screens.
+/// The numbers are kept until the next run starts, a continued run brings back its saved numbers.
+impl Plugin for RunStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_systems(OnEnter(GameState::Playing), reset_run_stats.run_if(not(resource_exists::<ContinueRun>())))
+            .add_systems(FixedUpdate, record_run_stats
+                .after(SimulationSet::World)
+                .run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Numbers of a whole run, or of one day and its night
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Tally {
+    pub kills_by_weapon: HashMap<String, u32>,
+    /// Trigger pulls of the player
+    pub shots: u32,
+    /// Projectiles of those shots
+    pub projectiles: u32,
+    /// Projectiles of the player that hit something
+    pub landed: u32,
+    pub chests: u32,
+    pub turrets: u32,
+    /// Damage that reached `Hp`
+    pub damage_taken: f32,
+    /// Damage the shield took
+    pub damage_absorbed: f32,
+    pub energy_collected: u32,
+    pub energy_spent: u32,
+}
+
+impl Tally {
+    pub fn kills(&self) -> u32 {
+        self.kills_by_weapon.values().sum()
+    }
+
+    /// Share of the projectiles that hit, from 0 to 1
+    pub fn accuracy(&self) -> f32 {
+        if self.projectiles == 0 {
+            return 0.0;
+        }
+        (self.landed as f32 / self.projectiles as f32).min(1.0)
+    }
+
+    /// Kills per weapon, most first
+    pub fn kills_sorted(&self) -> Vec<(&str, u32)> {
+        let mut kills: Vec<_> = self.kills_by_weapon.iter().map(|(name, count)| (name.as_str(), *count)).collect();
+        kills.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        kills
+    }
+
+    fn add_kill(&mut self, weapon: &str) {
+        *self.kills_by_weapon.entry(weapon.to_string()).or_default() += 1;
+    }
+}
+
+#[derive(Resource, Clone, Default, Debug, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Days fully survived
+    pub days_survived: i32,
+    pub total: Tally,
+    /// One entry per day and the night that follows it, the first day first
+    pub nights: Vec<Tally>,
+}
+
+impl RunStats {
+    /// The tally of `day`, which starts at 1
+    fn night(&mut self, day: i32) -> &mut Tally {
+        let index = (day.max(1) - 1) as usize;
+        if self.nights.len() <= index {
+            self.nights.resize_with(index + 1, Tally::default);
+        }
+        &mut self.nights[index]
+    }
+
+    /// Applies `change` to the whole run and to `day`
+    fn record(&mut self, day: i32, change: impl Fn(&mut Tally)) {
+        change(&mut self.total);
+        change(self.night(day));
+    }
+
+    /// Summary of the whole run, then a line per night
+    pub fn report(&self) -> String {
+        let total = &self.total;
+        let kills = total
+            .kills_sorted()
+            .iter()
+            .map(|(weapon, count)| format!("{} {}", weapon, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut report = format!(
+            "Days survived: {}\n\
+             Kills: {}{}\n\
+             Shots fired: {}, accuracy {:.0}%\n\
+             Chests opened: {}, turrets placed: {}\n\
+             Damage taken: {:.0}, {:.0} more stopped by the shield\n\
+             Energy collected: {}, spent: {}\n",
+            self.days_survived,
+            total.kills(),
+            if kills.is_empty() { String::new() } else { format!(" ({})", kills) },
+            total.shots,
+            total.accuracy() * 100.0,
+            total.chests,
+            total.turrets,
+            total.damage_taken,
+            total.damage_absorbed,
+            total.energy_collected,
+            total.energy_spent,
+        );
+        for (index, night) in self.nights.iter().enumerate() {
+            report += &format!(
+                "\nNight {}: {} kills, {} shots, {:.0}% accuracy, {} chests, {} turrets, {:.0} damage, +{}/-{} energy",
+                index + 1,
+                night.kills(),
+                night.shots,
+                night.accuracy() * 100.0,
+                night.chests,
+                night.turrets,
+                night.damage_taken,
+                night.energy_collected,
+                night.energy_spent,
+            );
+        }
+        report
+    }
+}
+
+fn reset_run_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats::default();
+}
+
+fn record_run_stats(
+    mut stats: ResMut<RunStats>,
+    day_night: Res<DayNight>,
+    mut kills: EventReader<EnemyKilled>,
+    mut shots: EventReader<ShotFired>,
+    mut landed: EventReader<BulletLanded>,
+    mut chests: EventReader<ChestOpened>,
+    mut turrets: EventReader<TurretPlaced>,
+    mut damage: EventReader<PlayerDamaged>,
+    query_player: Query<Entity, With<Player>>,
+    query_turrets: Query<(), With<Turret>>,
+) {
+    let day = day_night.day;
+    stats.days_survived = day - 1;
+    let player = query_player.get_single().ok();
+
+    for kill in kills.iter() {
+        let weapon = match (&kill.weapon, kill.killer) {
+            (Some(weapon), Some(killer)) if query_turrets.contains(killer) => format!("{} (turret)", weapon),
+            (Some(weapon), _) => weapon.clone(),
+            (None, _) => "Unknown".to_string(),
+        };
+        stats.record(day, |tally| tally.add_kill(&weapon));
+    }
+    for shot in shots.iter() {
+        stats.record(day, |tally| {
+            tally.shots += 1;
+            tally.projectiles += shot.projectiles;
+            tally.energy_spent += shot.energy.max(0) as u32;
+        });
+    }
+    for _ in landed.iter().filter(|hit| Some(hit.owner) == player) {
+        stats.record(day, |tally| tally.landed += 1);
+    }
+    for chest in chests.iter() {
+        let energy: u32 = chest.loot.iter().map(|loot| match loot {
+            Loot::Energy(count) => *count,
+            _ => 0,
+        }).sum();
+        stats.record(day, |tally| {
+            tally.chests += 1;
+            tally.energy_collected += energy;
+        });
+    }
+    for _ in turrets.iter() {
+        stats.record(day, |tally| tally.turrets += 1);
+    }
+    for hit in damage.iter() {
+        stats.record(day, |tally| {
+            tally.damage_taken += hit.amount;
+            tally.damage_absorbed += hit.absorbed;
+        });
+    }
+}
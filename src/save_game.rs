@@ -0,0 +1,426 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::Actions;
+use crate::characters::arsenal::Arsenal;
+use crate::characters::base_character::Hp;
+use crate::characters::damage::Shield;
+use crate::characters::enemy::{build_enemy_atlases, insert_enemy, Enemy, EnemyAtlases};
+use crate::characters::enemy_kinds::EnemyKind;
+use crate::characters::player::{spawn_player, Energy, Inventoty, Player, TemporaryItems, Weapon};
+use crate::characters::status_effects::StatusEffects;
+use crate::characters::turret::Turret;
+use crate::game_events::PhaseChanged;
+use crate::interactive_items::chest::Chest;
+use crate::loading::TextureAssets;
+use crate::map::{spawn_map, ChunkRecord, DayNight, Map, StoredChest, StoredTurret};
+use crate::replay::{start_recording, Recorder, Replay};
+use crate::rng::{fnv1a, reseed, GameRng};
+use crate::run_stats::RunStats;
+use crate::simulation::{Interpolated, SimulationTime};
+use crate::storage;
+use crate::GameState;
+
+pub const SAVE_FILE: &str = "run.save";
+
+const MAGIC: &[u8; 4] = b"PJSV";
+/// Bumped when [`SaveGame`] changes, older saves are refused rather than half loaded
+const VERSION: u16 = 4;
+const HEADER_SIZE: usize = 4 + 2 + 8;
+
+pub struct SaveGamePlugin;
+
+/// This plugin saves the run to [`SAVE_FILE`] in the data directory at every dawn and when the player
+/// saves and quits, and restores it when the run is continued from the menu.
+/// The save is deleted when the run ends. Replays never touch the save.
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), restore_world
+                .after(reseed)
+                .before(spawn_map)
+                .run_if(resource_exists::<ContinueRun>()))
+            .add_systems(OnEnter(GameState::Playing), (apply_deferred, restore_entities)
+                .chain()
+                .after(spawn_map)
+                .after(spawn_player)
+                .after(build_enemy_atlases)
+                .after(start_recording)
+                .run_if(resource_exists::<ContinueRun>()))
+            .add_systems(Update, (autosave_at_dawn, save_and_quit)
+                .run_if(in_state(GameState::Playing))
+                .run_if(not_replaying))
+            .add_systems(OnEnter(GameState::MenuDeath), delete_save.run_if(not_replaying))
+            .add_systems(OnEnter(GameState::MenuWin), delete_save.run_if(not_replaying));
+    }
+}
+
+/// The saved run to restore when `GameState::Playing` is entered
+#[derive(Resource)]
+pub struct ContinueRun(pub SaveGame);
+
+#[derive(Serialize, Deserialize)]
+struct SavedPlayer {
+    position: Vec3,
+    hp: f32,
+    shield: Option<Shield>,
+    energy: i32,
+    weapon: String,
+    effects: StatusEffects,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEnemy {
+    kind: EnemyKind,
+    position: Vec3,
+    strength: f32,
+    speed: f32,
+    hp: f32,
+    effects: StatusEffects,
+}
+
+/// Everything needed to continue a run. Weapons are saved by name and looked up in the [`Arsenal`].
+/// Status effects keep their weapon but not who applied them. Turrets are saved without their
+/// status effects, like when their chunk is unloaded.
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    seed: u64,
+    /// Word positions of the map, chests, loot and enemies streams of [`GameRng`]
+    rng: [u64; 4],
+    day_night: DayNight,
+    player: SavedPlayer,
+    inventory_turret: Option<String>,
+    offered_weapon: Option<String>,
+    offered_turret: Option<String>,
+    /// Seconds since the offer was made
+    offer_age: f64,
+    /// Chunks generated so far, with the content of the unloaded ones
+    chunks: HashMap<(i32, i32), ChunkRecord>,
+    /// Content of the loaded chunks
+    chests: Vec<StoredChest>,
+    turrets: Vec<StoredTurret>,
+    enemies: Vec<SavedEnemy>,
+    /// Statistics of the run so far, for the end screens and the run history
+    stats: RunStats,
+}
+
+impl SaveGame {
+    /// Header with [`MAGIC`], [`VERSION`] and a checksum of the body, then the body as RON
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let body = ron::to_string(self).map_err(|e| e.to_string())?;
+        let mut out = Vec::with_capacity(HEADER_SIZE + body.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&fnv1a(body.as_bytes()).to_le_bytes());
+        out.extend_from_slice(body.as_bytes());
+        Ok(out)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_SIZE || bytes[0..4] != MAGIC[..] {
+            return Err("not a save file".into());
+        }
+        let version = u16::from_le_bytes(bytes[4..6].try_into().unwrap());
+        if version != VERSION {
+            return Err(format!("unsupported save version {}", version));
+        }
+        let checksum = u64::from_le_bytes(bytes[6..14].try_into().unwrap());
+        let body = &bytes[HEADER_SIZE..];
+        if fnv1a(body) != checksum {
+            return Err("checksum mismatch, the file is corrupt".into());
+        }
+        ron::de::from_bytes(body).map_err(|e| e.to_string())
+    }
+
+    /// The saved run, if there is one and it can be read
+    pub fn load() -> Option<Self> {
+        let path = storage::data_file(SAVE_FILE)?;
+        let bytes = std::fs::read(&path).ok()?;
+        match Self::from_bytes(&bytes) {
+            Ok(save) => Some(save),
+            Err(e) => {
+                warn!("Can't load save {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = storage::data_file(SAVE_FILE) else {
+            return;
+        };
+        let bytes = match self.to_bytes() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!("Can't serialize the run: {}", e);
+                return;
+            }
+        };
+        match storage::write(&path, bytes) {
+            Ok(()) => info!("Run saved to {:?}", path),
+            Err(e) => warn!("Can't save the run to {:?}: {}", path, e),
+        }
+    }
+}
+
+/// The state of the run that goes into a [`SaveGame`]
+#[derive(SystemParam)]
+struct RunState<'w, 's> {
+    time: Res<'w, SimulationTime>,
+    rng: Res<'w, GameRng>,
+    day_night: Res<'w, DayNight>,
+    map: Res<'w, Map>,
+    inventory: Res<'w, Inventoty>,
+    temporary: Res<'w, TemporaryItems>,
+    stats: Res<'w, RunStats>,
+    player: Query<
+        'w,
+        's,
+        (&'static Transform, &'static Hp, Option<&'static Shield>, &'static Energy, &'static Weapon, &'static StatusEffects),
+        With<Player>,
+    >,
+    chests: Query<'w, 's, (&'static Transform, &'static Chest)>,
+    turrets: Query<'w, 's, (&'static Transform, &'static Hp, &'static Weapon), With<Turret>>,
+    enemies: Query<'w, 's, (&'static Transform, &'static Hp, &'static Enemy, &'static StatusEffects)>,
+}
+
+impl<'w, 's> RunState<'w, 's> {
+    fn capture(&self) -> Option<SaveGame> {
+        let (player_transform, hp, shield, energy, weapon, effects) = self.player.get_single().ok()?;
+        let rng = &self.rng;
+        Some(SaveGame {
+            seed: rng.seed,
+            rng: [rng.map.get_word_pos(), rng.chests.get_word_pos(), rng.loot.get_word_pos(), rng.enemies.get_word_pos()]
+                .map(|position| position as u64),
+            day_night: self.day_night.clone(),
+            player: SavedPlayer {
+                position: player_transform.translation,
+                hp: hp.0,
+                shield: shield.cloned(),
+                energy: energy.0,
+                weapon: weapon.name.clone(),
+                effects: effects.clone(),
+            },
+            inventory_turret: self.inventory.turret.as_ref().map(|turret| turret.name.clone()),
+            offered_weapon: self.temporary.weapon.as_ref().map(|weapon| weapon.name.clone()),
+            offered_turret: self.temporary.turret.as_ref().map(|turret| turret.name.clone()),
+            offer_age: self.time.elapsed_seconds_f64() - self.temporary.timestamp,
+            chunks: self.map.chunks.clone(),
+            chests: self.chests.iter().map(|(transform, chest)| StoredChest::new(transform, chest)).collect(),
+            turrets: self.turrets.iter().map(|(transform, hp, weapon)| StoredTurret::new(transform, hp, weapon)).collect(),
+            enemies: self.enemies.iter().map(|(transform, hp, enemy, effects)| SavedEnemy {
+                kind: enemy.kind,
+                position: transform.translation,
+                strength: transform.scale.x / enemy.kind.scale(),
+                speed: enemy.speed,
+                hp: hp.0,
+                effects: effects.clone(),
+            }).collect(),
+            stats: self.stats.clone(),
+        })
+    }
+}
+
+/// The [`Replay`] is gone once the run ends, the [`Recorder`] still knows the run was one
+fn not_replaying(replay: Option<Res<Replay>>, recorder: Res<Recorder>) -> bool {
+    replay.is_none() && !recorder.is_replay()
+}
+
+fn autosave_at_dawn(mut phases: EventReader<PhaseChanged>, run: RunState) {
+    if phases.iter().any(|phase| !phase.is_night) {
+        if let Some(save) = run.capture() {
+            save.save();
+        }
+    }
+}
+
+fn save_and_quit(mut actions: ResMut<Actions>, mut state: ResMut<NextState<GameState>>, run: RunState) {
+    if std::mem::take(&mut actions.save_and_quit.just_pressed) {
+        if let Some(save) = run.capture() {
+            save.save();
+        }
+        state.set(GameState::Menu);
+    }
+}
+
+fn delete_save() {
+    let Some(path) = storage::data_file(SAVE_FILE) else {
+        return;
+    };
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("Can't delete save {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Random streams and chunk records go back first, so the map spawned after it doesn't roll new chests
+fn restore_world(save: Res<ContinueRun>, mut rng: ResMut<GameRng>, mut map: ResMut<Map>) {
+    let save = &save.0;
+    *rng = GameRng::new(save.seed);
+    rng.map.set_word_pos(save.rng[0] as u128);
+    rng.chests.set_word_pos(save.rng[1] as u128);
+    rng.loot.set_word_pos(save.rng[2] as u128);
+    rng.enemies.set_word_pos(save.rng[3] as u128);
+    map.chunks = save.chunks.clone();
+}
+
+/// The player spawned for a new run takes the saved state, the rest of the world is spawned as saved
+fn restore_entities(
+    mut commands: Commands,
+    save: Res<ContinueRun>,
+    textures: Res<TextureAssets>,
+    arsenal: Res<Arsenal>,
+    atlases: Res<EnemyAtlases>,
+    mut recorder: ResMut<Recorder>,
+    mut day_night: ResMut<DayNight>,
+    mut inventory: ResMut<Inventoty>,
+    mut temporary: ResMut<TemporaryItems>,
+    mut stats: ResMut<RunStats>,
+    mut query_player: Query<(Entity, &mut Transform, &mut Interpolated, &mut Hp, &mut Energy, &mut Weapon), With<Player>>,
+) {
+    let save = &save.0;
+    *day_night = save.day_night.clone();
+    *stats = save.stats.clone();
+
+    if let Ok((player, mut transform, mut interpolated, mut hp, mut energy, mut weapon)) = query_player.get_single_mut() {
+        transform.translation = save.player.position;
+        *interpolated = Interpolated::new(save.player.position);
+        hp.0 = save.player.hp;
+        let mut player = commands.entity(player);
+        player.insert(save.player.effects.clone());
+        match &save.player.shield {
+            Some(shield) => player.insert(shield.clone()),
+            None => player.remove::<Shield>(),
+        };
+        energy.0 = save.player.energy;
+        *weapon = arsenal.get_or(&save.player.weapon, &arsenal.starting_weapon);
+    }
+    inventory.turret = save.inventory_turret.as_ref().map(|name| arsenal.get_or(name, &arsenal.starting_turret));
+    temporary.weapon = save.offered_weapon.as_ref().and_then(|name| arsenal.get(name).cloned());
+    temporary.turret = save.offered_turret.as_ref().and_then(|name| arsenal.get(name).cloned());
+    // The simulation time starts over, so the offer is dated back
+    temporary.timestamp = -save.offer_age;
+
+    for chest in save.chests.iter() {
+        chest.spawn(&mut commands, &textures, Color::WHITE);
+    }
+    for turret in save.turrets.iter() {
+        turret.spawn(&mut commands, &textures, &arsenal);
+    }
+    for saved in save.enemies.iter() {
+        let enemy = insert_enemy(&mut commands, &atlases, saved.kind, saved.position, saved.strength, saved.speed);
+        commands.entity(enemy).insert(Hp(saved.hp)).insert(saved.effects.clone());
+    }
+
+    // The run doesn't start from its seed alone anymore
+    recorder.resumed = true;
+    info!("Run continued on day {}", day_night.day);
+    commands.remove_resource::<ContinueRun>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::characters::status_effects::{ActiveEffect, Stacking, StatusEffect, StatusKind};
+    use crate::run_stats::Tally;
+
+    fn save() -> SaveGame {
+        let burn = ActiveEffect {
+            effect: StatusEffect {
+                kind: StatusKind::Burn,
+                duration: 3.0,
+                damage_per_second: 1.0,
+                speed_multiplier: 1.0,
+                stacking: Stacking::Stack { max: 3 },
+            },
+            remaining: 1.5,
+            stacks: 2,
+            by: None,
+            weapon: Some("Scorcher".into()),
+        };
+        let mut chunks = HashMap::default();
+        chunks.insert(
+            (1, -2),
+            ChunkRecord {
+                chests: Vec::new(),
+                turrets: vec![StoredTurret { position: Vec3::new(1.0, 2.0, 0.1), hp: 30.0, weapon: "Pistol".into() }],
+            },
+        );
+        let stats = RunStats {
+            days_survived: 2,
+            total: Tally { shots: 12, ..default() },
+            nights: Vec::new(),
+        };
+        SaveGame {
+            seed: 99,
+            rng: [1, 2, 3, 4],
+            day_night: DayNight::default(),
+            player: SavedPlayer {
+                position: Vec3::new(-10.0, 20.0, 0.1),
+                hp: 55.0,
+                shield: Some(Shield::new(25.0, 5.0, 3.0)),
+                energy: 120,
+                weapon: "Shotgun".into(),
+                effects: StatusEffects(vec![burn.clone()]),
+            },
+            inventory_turret: Some("Pistol".into()),
+            offered_weapon: None,
+            offered_turret: None,
+            offer_age: 1.25,
+            chunks,
+            chests: Vec::new(),
+            turrets: Vec::new(),
+            enemies: vec![SavedEnemy {
+                kind: EnemyKind::Tank,
+                position: Vec3::new(3.0, 4.0, 0.1),
+                strength: 1.2,
+                speed: 70.0,
+                hp: 12.0,
+                effects: StatusEffects(vec![burn]),
+            }],
+            stats,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = save().to_bytes().unwrap();
+        let loaded = SaveGame::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.to_bytes().unwrap(), bytes);
+
+        assert_eq!(loaded.seed, 99);
+        assert_eq!(loaded.player.shield.map(|shield| shield.value), Some(25.0));
+        let burn = &loaded.enemies[0].effects.0[0];
+        assert_eq!((burn.stacks, burn.remaining), (2, 1.5));
+        assert_eq!(burn.weapon.as_deref(), Some("Scorcher"));
+        assert_eq!(loaded.chunks[&(1, -2)].turrets.len(), 1);
+        assert_eq!(loaded.stats.total.shots, 12);
+    }
+
+    #[test]
+    fn corrupted_body_is_refused() {
+        let mut bytes = save().to_bytes().unwrap();
+        let last = bytes.len() - 2;
+        bytes[last] ^= 1;
+        let error = SaveGame::from_bytes(&bytes).err().unwrap();
+        assert!(error.contains("checksum"), "{}", error);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let mut bytes = save().to_bytes().unwrap();
+        bytes[4..6].copy_from_slice(&(VERSION - 1).to_le_bytes());
+        let error = SaveGame::from_bytes(&bytes).err().unwrap();
+        assert!(error.contains("version"), "{}", error);
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        assert!(SaveGame::from_bytes(b"").is_err());
+        let mut bytes = save().to_bytes().unwrap();
+        bytes[0] = b'X';
+        assert!(SaveGame::from_bytes(&bytes).is_err());
+    }
+}
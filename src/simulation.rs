@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::actions::bindings::Bindings;
+use crate::actions::{reset_actions, Actions};
+use crate::characters::arsenal::ArsenalPlugin;
+use crate::characters::bullets::BulletPlugin;
+use crate::characters::cleaner::CleanerPlugin;
+use crate::characters::damage::DamagePlugin;
+use crate::characters::enemy::EnemyPlugin;
+use crate::characters::player::PlayerPlugin;
+use crate::characters::status_effects::StatusEffectsPlugin;
+use crate::characters::turret::TurretPlugin;
+use crate::game_events::GameEventsPlugin;
+use crate::map::MapPlugin;
+use crate::rng::RngPlugin;
+use crate::run_stats::RunStatsPlugin;
+use crate::spatial_grid::SpatialGridPlugin;
+use crate::ui::UiLog;
+use crate::GameState;
+
+/// Length of one simulation step, the same on every machine and at every frame rate
+pub const SIMULATION_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// The game rules only: no window, input devices, rendering, egui or audio are touched,
+/// so it also runs under `MinimalPlugins`. Whoever adds it fills [`Actions`].
+///
+/// Gameplay runs in `FixedUpdate`, in [`SimulationSet`] order, one [`SIMULATION_STEP`] at a time.
+/// Gameplay systems read [`SimulationTime`] instead of `Time`, and entities that move carry
+/// [`Interpolated`] so they are drawn smoothly between two steps.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .insert_resource(FixedTime::new(SIMULATION_STEP))
+            .init_resource::<SimulationTime>()
+            .init_resource::<Actions>()
+            .init_resource::<Bindings>()
+            .init_resource::<UiLog>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    SimulationSet::Player,
+                    SimulationSet::Turrets,
+                    SimulationSet::Enemies,
+                    SimulationSet::Collisions,
+                    SimulationSet::Bullets,
+                    SimulationSet::Effects,
+                    SimulationSet::Damage,
+                    SimulationSet::World,
+                )
+                    .chain(),
+            )
+            .add_systems(OnEnter(GameState::Playing), (reset_actions, reset_simulation_time))
+            .add_systems(First, restore_transforms.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                FixedUpdate,
+                (
+                    (advance_simulation_time, store_previous_transforms).before(SimulationSet::Player),
+                    (store_current_transforms, consume_presses).after(SimulationSet::World),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                PostUpdate,
+                interpolate_transforms
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_plugins((
+                GameEventsPlugin,
+                ArsenalPlugin,
+                CleanerPlugin,
+                RngPlugin,
+                PlayerPlugin,
+                EnemyPlugin,
+                TurretPlugin,
+                MapPlugin,
+                BulletPlugin,
+                SpatialGridPlugin,
+                StatusEffectsPlugin,
+                DamagePlugin,
+                RunStatsPlugin,
+            ));
+    }
+}
+
+/// Order of the gameplay systems inside a simulation step, so every step runs them the same way
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum SimulationSet {
+    Player,
+    Turrets,
+    Enemies,
+    /// The [`SpatialGrid`](crate::spatial_grid::SpatialGrid) is rebuilt, then contact damage is dealt
+    Collisions,
+    Bullets,
+    /// Status effects applied this step take hold, then all of them tick
+    Effects,
+    /// The damage sent during the step is dealt
+    Damage,
+    World,
+}
+
+/// Clock of the run, advanced by one [`SIMULATION_STEP`] per step and reset when a run starts.
+/// Mirrors the parts of `Time` gameplay uses.
+#[derive(Resource, Default)]
+pub struct SimulationTime {
+    delta: Duration,
+    elapsed: Duration,
+}
+
+impl SimulationTime {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn elapsed_seconds_f64(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+}
+
+/// Translation of an entity at the last two simulation steps, the `Transform` shows a blend of both.
+/// Gameplay always sees the latest step.
+#[derive(Component)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl Interpolated {
+    pub fn new(translation: Vec3) -> Self {
+        Self { previous: translation, current: translation }
+    }
+}
+
+/// The step accumulator is emptied too, so time spent in the menus doesn't shift the steps of the run
+fn reset_simulation_time(mut time: ResMut<SimulationTime>, mut fixed_time: ResMut<FixedTime>) {
+    *time = SimulationTime::default();
+    *fixed_time = FixedTime::new(SIMULATION_STEP);
+}
+
+fn advance_simulation_time(fixed_time: Res<FixedTime>, mut time: ResMut<SimulationTime>) {
+    time.delta = fixed_time.period;
+    time.elapsed += fixed_time.period;
+}
+
+/// Undo the blend of the previous frame before anything reads positions
+fn restore_transforms(mut query: Query<(&mut Transform, &Interpolated)>) {
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.current;
+    }
+}
+
+fn store_previous_transforms(mut query: Query<&mut Interpolated>) {
+    for mut interpolated in query.iter_mut() {
+        interpolated.previous = interpolated.current;
+    }
+}
+
+fn store_current_transforms(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.current = transform.translation;
+    }
+}
+
+pub fn interpolate_transforms(fixed_time: Res<FixedTime>, mut query: Query<(&mut Transform, &Interpolated)>) {
+    let alpha = (fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32()).clamp(0.0, 1.0);
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.previous.lerp(interpolated.current, alpha);
+    }
+}
+
+/// A press is seen by exactly one simulation step, even when a frame runs none or several
+fn consume_presses(mut actions: ResMut<Actions>) {
+    actions.consume_presses();
+}
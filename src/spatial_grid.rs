@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::characters::base_character::Hp;
+use crate::collision::Collider;
+use crate::simulation::SimulationSet;
+use crate::GameState;
+
+/// Side of a grid cell in pixels, about the size of the biggest enemies
+const CELL_SIZE: f32 = 128.0;
+
+/// Keeps [`SpatialGrid`] up to date with everything that can be hit
+pub struct SpatialGridPlugin;
+
+impl Plugin for SpatialGridPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialGrid>().add_systems(
+            FixedUpdate,
+            update_spatial_grid
+                .in_set(SimulationSet::Collisions)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Uniform grid of the entities with `Hp` and a [`Collider`], rebuilt every simulation step
+/// once the enemies have moved. Lookups return candidates, whether they really touch is up to
+/// [`Collider::overlaps`]. Systems that run before [`SimulationSet::Collisions`] see the previous step.
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cells: HashMap<IVec2, Vec<Entity>>,
+    /// Largest [`Collider::extent`] in the grid, entities are only stored in the cell of their center
+    max_extent: f32,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self { cells: HashMap::default(), max_extent: 0.0 }
+    }
+}
+
+impl SpatialGrid {
+    pub fn clear(&mut self) {
+        // Keep the allocations, the same cells are mostly used again
+        for entities in self.cells.values_mut() {
+            entities.clear();
+        }
+        self.max_extent = 0.0;
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2, extent: f32) {
+        self.cells.entry(cell(position)).or_default().push(entity);
+        self.max_extent = self.max_extent.max(extent);
+    }
+
+    /// Entities whose collider may reach into the circle at `center`, in a stable order
+    pub fn near(&self, center: Vec2, radius: f32) -> impl Iterator<Item = Entity> + '_ {
+        let reach = radius + self.max_extent;
+        let min = cell(center - Vec2::splat(reach));
+        let max = cell(center + Vec2::splat(reach));
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+fn cell(position: Vec2) -> IVec2 {
+    (position / CELL_SIZE).floor().as_ivec2()
+}
+
+pub fn update_spatial_grid(mut grid: ResMut<SpatialGrid>, query: Query<(Entity, &Transform, &Collider), With<Hp>>) {
+    grid.clear();
+    for (entity, transform, collider) in query.iter() {
+        grid.insert(entity, transform.translation.truncate(), collider.extent(transform));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn near_finds_every_overlap_brute_force_finds() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut scatter = |scale: f32| {
+            let position = Vec3::new(rng.gen_range(-1000.0..1000.0), rng.gen_range(-1000.0..1000.0), 0.0);
+            Transform::from_translation(position).with_scale(Vec3::splat(scale))
+        };
+        let collider = Collider::circle(80.0);
+        // Mixed sizes, so the biggest extent decides how far lookups reach
+        let targets: Vec<(Entity, Transform)> = (0..500)
+            .map(|i| (Entity::from_raw(i), scatter(if i % 10 == 0 { 0.6 } else { 0.2 })))
+            .collect();
+        let mut grid = SpatialGrid::default();
+        for (entity, transform) in targets.iter() {
+            grid.insert(*entity, transform.translation.truncate(), collider.extent(transform));
+        }
+
+        let probe = Collider::circle(16.0);
+        let mut hits = 0;
+        for _ in 0..200 {
+            let transform = scatter(1.0);
+            let mut brute_force: Vec<Entity> = targets
+                .iter()
+                .filter(|(_, target)| probe.overlaps(&transform, &collider, target))
+                .map(|(entity, _)| *entity)
+                .collect();
+            let mut found: Vec<Entity> = grid
+                .near(transform.translation.truncate(), probe.extent(&transform))
+                .filter(|entity| {
+                    let target = &targets[entity.index() as usize].1;
+                    probe.overlaps(&transform, &collider, target)
+                })
+                .collect();
+            brute_force.sort();
+            found.sort();
+            assert_eq!(found, brute_force);
+            hits += found.len();
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn near_is_empty_after_clear() {
+        let mut grid = SpatialGrid::default();
+        grid.insert(Entity::from_raw(0), Vec2::new(-5.0, 5.0), 10.0);
+        assert_eq!(grid.near(Vec2::ZERO, 1.0).count(), 1);
+        grid.clear();
+        assert_eq!(grid.near(Vec2::ZERO, 1.0).count(), 0);
+    }
+}
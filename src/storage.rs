@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+/// Location of a config file in the platform config directory
+/// (e.g. `~/.config/pashtet_jam_2/` on Linux). `None` when there is no writable
+/// file system, like on the web.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "pashtet_jam_2")?;
+    Some(dirs.config_dir().join(name))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn config_file(_name: &str) -> Option<PathBuf> {
+    None
+}
+
+/// Location of a file in the platform data directory
+/// (e.g. `~/.local/share/pashtet_jam_2/` on Linux), for things the game produces itself.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "pashtet_jam_2")?;
+    Some(dirs.data_dir().join(name))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn data_file(_name: &str) -> Option<PathBuf> {
+    None
+}
+
+pub fn read_to_string(path: &PathBuf) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+pub fn write(path: &PathBuf, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)
+}
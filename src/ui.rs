@@ -1,14 +1,14 @@
 use bevy::prelude::*;
 use bevy_egui::{EguiContexts, egui::{self, FontId, RichText, Color32, Frame} };
 
-use crate::{GameState, characters::{base_character::Hp, player::{Player, Energy, Weapon, Inventoty}}, map::DayNight};
+use crate::{GameState, actions::{bindings::Bindings, game_control::GameControl}, characters::{base_character::Hp, damage::{MaxHp, Shield}, player::{Player, Energy, Weapon, Inventoty}, status_effects::{ActiveEffect, StatusEffects, StatusKind}}, game_events::{ChestOpened, Loot, WeaponSlot, WeaponSwapped}, map::DayNight, rng::GameRng, simulation::SimulationTime};
 
 
 pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<UiLog>()
-            .add_systems(Update, ui.run_if(in_state(GameState::Playing)));
+            .add_systems(Update, (log_game_events, ui).chain().run_if(in_state(GameState::Playing)));
     }
 }
 
@@ -18,16 +18,107 @@ pub struct UiLog {
     pub message_time_stamp: f64
 }
 
+/// Turns the gameplay events worth telling the player into the message at the bottom of the screen
+fn log_game_events(
+    time: Res<SimulationTime>,
+    bindings: Res<Bindings>,
+    mut ui_log: ResMut<UiLog>,
+    mut chests: EventReader<ChestOpened>,
+    mut swaps: EventReader<WeaponSwapped>,
+) {
+    let swap_key = bindings.describe(GameControl::SwapWeapon);
+    for chest in chests.iter() {
+        let mut message = "New items: ".to_string();
+        for loot in chest.loot.iter() {
+            message += &match loot {
+                Loot::Energy(count) => format!("{} energy", count),
+                Loot::Weapon(weapon) => format!("Found {} ({}), press {} to change", weapon.name, weapon.stats(), swap_key),
+                Loot::Turret(weapon) => format!("Found turret {} ({})", weapon.name, weapon.stats()),
+                Loot::TurretOffer(weapon) => format!("New turret {} ({}), press {} to change", weapon.name, weapon.stats(), swap_key),
+            };
+        }
+        ui_log.last_message = message;
+        ui_log.message_time_stamp = time.elapsed_seconds_f64();
+    }
+    for swap in swaps.iter() {
+        ui_log.last_message = match swap.slot {
+            WeaponSlot::Hand => format!("Weapon changed to {}", swap.new),
+            WeaponSlot::Turret => format!("Turret changed to {}", swap.new),
+        };
+        ui_log.message_time_stamp = time.elapsed_seconds_f64();
+    }
+}
+
+const STATUS_ICON_SIZE: f32 = 36.0;
+
+/// An icon of the effect with its stacks, the part of it that is still to run and its name on hover
+fn status_icon(ui: &mut egui::Ui, active: &ActiveEffect) {
+    let [r, g, b, _] = active.effect.kind.color().as_rgba_u8();
+    let color = Color32::from_rgb(r, g, b);
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(STATUS_ICON_SIZE, STATUS_ICON_SIZE), egui::Sense::hover());
+    let painter = ui.painter();
+    let center = rect.center();
+    let radius = STATUS_ICON_SIZE * 0.3;
+    let stroke = egui::Stroke::new(3.0, Color32::WHITE);
+
+    painter.rect_filled(rect, 6.0, color);
+    // Darkens from the top as the effect runs out
+    if active.effect.duration > 0.0 {
+        let spent = (1.0 - active.remaining / active.effect.duration).clamp(0.0, 1.0);
+        let spent_rect = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), rect.height() * spent));
+        painter.rect_filled(spent_rect, 6.0, Color32::from_black_alpha(110));
+    }
+    match active.effect.kind {
+        StatusKind::Burn => {
+            let points = vec![
+                center + egui::vec2(0.0, -radius),
+                center + egui::vec2(radius * 0.7, radius * 0.6),
+                center + egui::vec2(-radius * 0.7, radius * 0.6),
+            ];
+            painter.add(egui::Shape::convex_polygon(points, Color32::WHITE, egui::Stroke::default()));
+        }
+        StatusKind::Poison => {
+            painter.circle_filled(center + egui::vec2(0.0, radius * 0.25), radius * 0.6, Color32::WHITE);
+            painter.line_segment([center + egui::vec2(0.0, -radius), center + egui::vec2(0.0, -radius * 0.2)], stroke);
+        }
+        StatusKind::Slow => {
+            for offset in [-radius * 0.45, radius * 0.35] {
+                let tip = center + egui::vec2(0.0, offset + radius * 0.4);
+                painter.line_segment([tip + egui::vec2(-radius * 0.6, -radius * 0.5), tip], stroke);
+                painter.line_segment([tip, tip + egui::vec2(radius * 0.6, -radius * 0.5)], stroke);
+            }
+        }
+        StatusKind::Freeze => {
+            for angle in [0.0, std::f32::consts::FRAC_PI_3, 2.0 * std::f32::consts::FRAC_PI_3] {
+                let arm = egui::vec2(angle.cos(), angle.sin()) * radius;
+                painter.line_segment([center - arm, center + arm], stroke);
+            }
+        }
+    }
+    if active.stacks > 1 {
+        painter.text(
+            rect.right_bottom() - egui::vec2(2.0, 0.0),
+            egui::Align2::RIGHT_BOTTOM,
+            active.stacks.to_string(),
+            FontId::monospace(14.0),
+            Color32::WHITE,
+        );
+    }
+    response.on_hover_text(format!("{} {:.1}s", active.effect.kind.name(), active.remaining));
+}
+
 pub fn ui(    
-    time: Res<Time>,
+    time: Res<SimulationTime>,
     mut contexts: EguiContexts,
     ui_log: Res<UiLog>,
     day_night: Res<DayNight>,
     inventory: Res<Inventoty>,
-    query: Query<(&Hp, &Energy, &Weapon), With<Player>>
+    bindings: Res<Bindings>,
+    rng: Res<GameRng>,
+    query: Query<(&Hp, &MaxHp, Option<&Shield>, &Energy, &Weapon, Option<&StatusEffects>), With<Player>>
 ){
     let current_time = time.elapsed_seconds_f64();
-    let (hp, energy, weapon) = query.single();
+    let (hp, max_hp, shield, energy, weapon, effects) = query.single();
 
     egui::TopBottomPanel::bottom("Down")
         .frame(Frame{
@@ -40,22 +131,33 @@ pub fn ui(
 
             ui.vertical_centered_justified(|ui|{
                 if hp.0 > 0.0 {
-                    let color = if hp.0 > 70.0 {
+                    let percent = hp.0 / max_hp.0 * 100.0;
+                    let color = if percent > 70.0 {
                         Color32::DARK_GREEN
-                    } else if hp.0 > 35.0 {
+                    } else if percent > 35.0 {
                         Color32::GOLD
                     } else {
                         Color32::DARK_RED
                     };
     
-                    ui.label(RichText::new(format!("Your health status: {} %", hp.0 as i32)).color(color).font(FontId::monospace(24.0)));
+                    ui.label(RichText::new(format!("Your health status: {} %", percent as i32)).color(color).font(FontId::monospace(24.0)));
+                    if let Some(shield) = shield {
+                        ui.label(RichText::new(format!("Shield: {}/{}", shield.value as i32, shield.max as i32)).color(Color32::DARK_BLUE).font(FontId::monospace(20.0)));
+                    }
                 } else {
                     ui.label(RichText::new(format!("You dead")).color(Color32::DARK_RED).font(FontId::monospace(24.0)));
                 }
+                if let Some(effects) = effects.filter(|effects| !effects.0.is_empty()) {
+                    ui.horizontal_wrapped(|ui| {
+                        for active in effects.0.iter() {
+                            status_icon(ui, active);
+                        }
+                    });
+                }
                 ui.label(RichText::new(format!("Energy left: {}/500", energy.0)).font(FontId::monospace(24.0)).color(Color32::BLACK));
-                ui.label(RichText::new(format!("Current weapon: {}", weapon.name)).font(FontId::monospace(24.0)).color(Color32::BLACK));
+                ui.label(RichText::new(format!("Current weapon: {} ({})", weapon.name, weapon.stats())).font(FontId::monospace(24.0)).color(Color32::BLACK));
                 if let Some(turret) = &inventory.turret {
-                    ui.label(RichText::new(format!("Turret equiped: {}, E to place", turret.name)).font(FontId::monospace(24.0)).color(Color32::BLACK));
+                    ui.label(RichText::new(format!("Turret equiped: {} ({}), {} to place", turret.name, turret.stats(), bindings.describe(GameControl::PlaceTurret))).font(FontId::monospace(24.0)).color(Color32::BLACK));
                 }
             });
  
@@ -103,7 +205,7 @@ pub fn ui(
                         format!("Night time left: {}:{}", time_min_night, time_sec_night as i32 - time_min_night * 60)
                     ).font(FontId::monospace(20.0)).color(Color32::BLACK));
                 }
-                
+                ui.label(RichText::new(format!("Seed: {}", rng.seed)).font(FontId::monospace(20.0)).color(Color32::BLACK));
             })
         });
 }
\ No newline at end of file